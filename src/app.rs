@@ -1,33 +1,299 @@
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, BTreeSet},
     error::Error,
-    path::{Path, PathBuf},
+    ops::RangeInclusive,
+    time::{Duration, Instant},
 };
 
-use ratatui::widgets::ListState;
-use tui_scrollview::ScrollViewState;
+use chrono::{DateTime, Local, Utc};
+use ratatui::widgets::{ListState, TableState};
+use regex::{Regex, RegexBuilder, RegexSet};
+use reqwest::Url;
+use tempfile::NamedTempFile;
 
-use crate::jenkins::{
-    fetch_jenkins_results, read_jenkins_config_file, JenkinsResult, JenkinsServer,
+use crate::{
+    causes::FailureCauses,
+    config::{AppConfig, ConfigSource},
+    diff::LogDiff,
+    event::{Event, EventSender},
+    export,
+    folding::{fold_at, FoldOptions, FoldedLog, Folds, TimestampMode},
+    jenkins::{
+        fetch_build_timings, fetch_jenkins_results, fetch_logs, fetch_new_logs, BuildState,
+        JenkinsResult, JenkinsServer, JobSort,
+    },
+    keymap::{KeyChord, Keymap},
+    layout::{PaneAreas, PaneBorder, PaneLayout},
+    log_store::{LogLimit, LogStore},
+    log_view::{Log, LogScrollState, LogSearch, ShownLog},
+    palette::{Palette, Suggestion},
+    record::HttpMode,
+    session::Session,
+    theme::Theme,
 };
 
+/// a jenkins server, along with its jobs so they can't end up with another server's
+#[derive(Debug)]
+pub struct StatefulServer {
+    /// connection info
+    pub server: JenkinsServer,
+    /// jobs in this server, once they've been fetched or read from the cache
+    pub jobs: Option<StatefulJobs>,
+}
+
 #[derive(Debug, Default)]
 pub struct StatefulServers {
-    /// map of server name (ini/toml table title) to connection info
-    pub servers: BTreeMap<String, JenkinsServer>,
+    /// map of server name (ini/toml table title) to the server and its jobs
+    pub servers: BTreeMap<String, StatefulServer>,
     /// tracks the active jenkins server
     pub server_state: ListState,
+    /// every server's builds together, for the all-servers view
+    pub all_servers: Option<StatefulJobs>,
+}
+
+impl StatefulServers {
+    /// where the all-servers view is in the server list: after the servers, if there's more
+    /// than one of them
+    pub fn all_servers_idx(&self) -> Option<usize> {
+        let count = self.servers.len();
+        (count > 1).then_some(count)
+    }
+
+    /// whether the all-servers view is selected
+    pub fn all_servers_selected(&self) -> bool {
+        self.all_servers_idx().is_some() && self.server_state.selected() == self.all_servers_idx()
+    }
+
+    /// name of the selected server, unless it's the all-servers view (or nothing) that's
+    /// selected
+    pub fn selected_name(&self) -> Option<&str> {
+        let idx = self.server_state.selected()?;
+        self.servers.keys().nth(idx).map(String::as_str)
+    }
+
+    /// where the server called `name` is in the server list
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.servers.keys().position(|server| server == name)
+    }
+
+    /// jobs of the selected server (or every server, in the all-servers view), if they've been
+    /// fetched
+    pub fn selected_jobs(&self) -> Option<&StatefulJobs> {
+        if self.all_servers_selected() {
+            return self.all_servers.as_ref();
+        }
+        let idx = self.server_state.selected()?;
+        self.servers.values().nth(idx)?.jobs.as_ref()
+    }
+
+    pub fn selected_jobs_mut(&mut self) -> Option<&mut StatefulJobs> {
+        if self.all_servers_selected() {
+            return self.all_servers.as_mut();
+        }
+        let idx = self.server_state.selected()?;
+        self.servers.values_mut().nth(idx)?.jobs.as_mut()
+    }
+
+    /// the selected build, along with the server it's from
+    fn selected_job_mut(&mut self) -> Option<(&mut JenkinsResult, &mut JenkinsServer)> {
+        let (stateful_jobs, server) = if self.all_servers_selected() {
+            let stateful_jobs = self.all_servers.as_mut()?;
+            let job = stateful_jobs
+                .jobs
+                .get(stateful_jobs.job_state.selected()?)?;
+            let server = self.servers.get_mut(job.server.as_deref()?)?;
+            (stateful_jobs, &mut server.server)
+        } else {
+            let idx = self.server_state.selected()?;
+            let entry = self.servers.values_mut().nth(idx)?;
+            (entry.jobs.as_mut()?, &mut entry.server)
+        };
+        let job = stateful_jobs
+            .jobs
+            .get_mut(stateful_jobs.job_state.selected()?)?;
+        Some((job, server))
+    }
+
+    /// the build at `link`, from a list that has its logs loaded if any does
+    pub fn find_build(&self, link: &Url) -> Option<&JenkinsResult> {
+        let mut builds = self
+            .servers
+            .values()
+            .filter_map(|entry| entry.jobs.as_ref())
+            .chain(self.all_servers.as_ref())
+            .flat_map(|stateful_jobs| &stateful_jobs.jobs)
+            .filter(|job| job.link == *link);
+        let first = builds.next()?;
+        Some(
+            std::iter::once(first)
+                .chain(builds)
+                .find(|job| !job.logs.is_empty())
+                .unwrap_or(first),
+        )
+    }
+
+    /// every server's jobs, and the all-servers view's
+    fn all_jobs_mut(&mut self) -> impl Iterator<Item = &mut StatefulJobs> {
+        self.servers
+            .values_mut()
+            .filter_map(|entry| entry.jobs.as_mut())
+            .chain(self.all_servers.as_mut())
+    }
 }
 
-// TODO: figure out if we should shove this into the JenkinsServer struct
 #[derive(Debug, Clone, Default)]
 pub struct StatefulJobs {
     /// jobs in this server
     pub jobs: Vec<JenkinsResult>,
     /// tracks the selected job
-    pub job_state: ListState,
+    pub job_state: TableState,
+    /// when the jobs were fetched, if they came from the cache rather than the server
+    pub stale_since: Option<DateTime<Local>>,
+}
+
+impl StatefulJobs {
+    /// link of the selected build, if there is one. builds are told apart by their links, since
+    /// servers running the same jobs have builds with the same names and numbers.
+    fn selected_build(&self) -> Option<Url> {
+        let job = self.jobs.get(self.job_state.selected()?)?;
+        Some(job.link.clone())
+    }
+
+    /// select the build at `link`, or nothing if it isn't there
+    fn select_build(&mut self, link: Option<Url>) {
+        self.job_state
+            .select(link.and_then(|link| self.jobs.iter().position(|job| job.link == link)));
+    }
+
+    /// sort the jobs, keeping the selected build selected
+    pub fn sort(&mut self, sort: JobSort) {
+        let selected = self.selected_build();
+        let now = Utc::now();
+        self.jobs.sort_by(|a, b| sort.compare(a, b, now));
+        if selected.is_some() {
+            self.select_build(selected);
+        }
+    }
+}
+
+/// fetch a server's builds, along with how long they took if the JSON API says
+async fn fetch_server_jobs(
+    jenkins_server: &mut JenkinsServer,
+) -> Result<Vec<JenkinsResult>, Box<dyn Error>> {
+    let mut jobs: Vec<JenkinsResult> = fetch_jenkins_results(jenkins_server).await?.collect();
+    // not every server lets the JSON API be used, so timings are just nice to have, and once
+    // they've failed they aren't tried again
+    if jenkins_server.timings_unavailable.is_none() {
+        match fetch_build_timings(jenkins_server).await {
+            Ok(timings) => {
+                for job in &mut jobs {
+                    job.timing = timings.get(&job.link).copied();
+                }
+            }
+            Err(e) => jenkins_server.timings_unavailable = Some(e.to_string()),
+        }
+    }
+    Ok(jobs)
+}
+
+/// a server's jobs, fetched in the background
+#[derive(Debug)]
+pub struct FetchedJobs {
+    /// name of the server they're from
+    pub server: String,
+    /// the copy of the server they were fetched with, which knows how it's been behaving since
+    pub jenkins_server: JenkinsServer,
+    pub jobs: Result<Vec<JenkinsResult>, String>,
+}
+
+/// how much of a build's logs to fetch
+#[derive(Debug, Clone, Copy)]
+enum LogFetch {
+    /// all of them, or as much as the limit allows
+    Whole(LogLimit),
+    /// whatever's been logged since this many bytes in
+    Since(u64),
+}
+
+/// a build's logs, as they were fetched
+#[derive(Debug)]
+pub enum FetchedLog {
+    Whole(Log),
+    /// what was logged after `start` bytes, up to `next_start`
+    Appended {
+        start: u64,
+        text: String,
+        next_start: u64,
+    },
+}
+
+/// a build's logs, fetched in the background
+#[derive(Debug)]
+pub struct FetchedLogs {
+    /// name of the server they're from
+    pub server: String,
+    /// the copy of the server they were fetched with, which knows how it's been behaving since
+    pub jenkins_server: JenkinsServer,
+    /// the build they're for
+    pub link: Url,
+    /// whether all of them were asked for, no matter how big they are
+    pub full: bool,
+    pub logs: Result<FetchedLog, String>,
+}
+
+/// a build to start once the user says so
+#[derive(Debug, Clone)]
+pub struct BuildTrigger {
+    /// name of the job
+    pub name: String,
+    /// name of the server it's on
+    pub server: String,
+    /// the job's URL
+    pub link: Url,
+}
+
+/// a diff of two builds' logs, worked out in the background
+#[derive(Debug)]
+pub struct BuildDiff {
+    /// link of the marked build
+    pub marked: Url,
+    /// link of the build it was compared against
+    pub selected: Url,
+    pub diff: LogDiff,
 }
 
+/// how the build at `link` is shown, if `folded` is for it
+fn folded_for<'a>(folded: &'a Option<(Url, FoldedLog)>, link: &Url) -> Option<&'a FoldedLog> {
+    folded
+        .as_ref()
+        .filter(|(folded_link, _)| folded_link == link)
+        .map(|(_, folded)| folded)
+}
+
+/// the selected build's logs as they're shown: folded, if they are, or as they are otherwise.
+/// takes the fields it needs rather than the whole `App`, so the scroll state can be borrowed
+/// at the same time.
+pub fn shown_logs<'a>(
+    servers: &'a StatefulServers,
+    folded: &'a Option<(Url, FoldedLog)>,
+) -> Option<ShownLog<'a>> {
+    let stateful_jobs = servers.selected_jobs()?;
+    let job = stateful_jobs
+        .jobs
+        .get(stateful_jobs.job_state.selected()?)?;
+    Some(match folded_for(folded, &job.link) {
+        Some(folded) => ShownLog::Folded(&job.logs, folded),
+        None => ShownLog::Whole(&job.logs),
+    })
+}
+
+/// how much of the screen a pane grows or shrinks by at a time
+const RESIZE_STEP: i16 = 5;
+
+/// how long the mouse wheel has to leave the server or build selection alone before the
+/// selected server's jobs or build's logs are fetched
+const WHEEL_SETTLE: Duration = Duration::from_millis(300);
+
 /// application state
 #[derive(Debug)]
 pub struct App {
@@ -38,37 +304,152 @@ pub struct App {
     /// tracks the active pane
     pub active_pane: i8,
 
-    /// path to JJB config
-    jenkins_config_path: PathBuf,
+    /// where to (re-)read the config from
+    config_source: ConfigSource,
+    /// app settings, re-read along with the servers
+    pub config: AppConfig,
+    /// whether requests are live, recorded, or replayed
+    http_mode: HttpMode,
     pub servers: StatefulServers,
-    pub jobs: HashMap<usize, StatefulJobs>,
-    pub log_scroll_state: ScrollViewState,
+    pub log_scroll_state: LogScrollState,
     pub wrap_logs: bool,
+    /// how the panes are arranged, and how big they are
+    pub layout: PaneLayout,
+    /// whether the active pane is taking up the whole screen
+    pub zoomed: bool,
+    pub areas: PaneAreas,
+    /// border being dragged with the mouse, if there is one
+    pub dragging: Option<PaneBorder>,
+    /// whether line numbers are shown next to the logs
+    pub line_numbers: bool,
+    /// what keys do, read from the keymap file
+    pub keymap: Keymap,
+    /// how everything looks, from the config and theme file
+    pub theme: Theme,
+    /// how every server's jobs are sorted
+    pub job_sort: JobSort,
+    /// keys typed so far of a sequence like `gg`
+    pub pending_keys: Vec<KeyChord>,
+    /// search being typed, if there is one
+    pub prompt: Option<String>,
+    /// last search of the selected build's logs, while its matches are highlighted
+    pub search: Option<LogSearch>,
+    /// how far the help overlay is scrolled, while it's shown
+    pub help_scroll: Option<u16>,
+    /// command being typed into the command palette, if it's open
+    pub palette: Option<Palette>,
+    /// build waiting on a yes or no before it's started
+    pub confirm_trigger: Option<BuildTrigger>,
+    /// what makes a log line a problem, compiled from the config
+    problem_patterns: RegexSet,
+    /// known failure causes to look for in logs
+    failure_causes: FailureCauses,
+    /// noise to normalize away before diffing logs, compiled from the config
+    diff_normalize: Vec<Regex>,
+    /// link of the build marked for comparing against another; its logs are kept in memory
+    pub marked: Option<Url>,
+    /// the diff of the marked build's logs against the selected build's is shown, or being
+    /// worked out
+    diffing: bool,
+    /// diff of the marked build's logs against the selected build's, once it's been worked out
+    pub diff: Option<BuildDiff>,
+    /// which folds are collapsed, and what's hidden, in the selected build's logs
+    pub fold_options: FoldOptions,
+    /// the selected build's pipeline blocks
+    folds: Folds,
+    /// the selected build's logs as they're shown, if that's any different from how they are,
+    /// along with which build they're for
+    pub folded: Option<(Url, FoldedLog)>,
+    /// whether the list of the selected build's problems is shown under its logs
+    pub show_problems: bool,
+    /// don't jump to the first problem of the next build to open, since the scroll position was
+    /// just restored
+    keep_log_scroll: bool,
+    /// when the selected server's jobs were last automatically refreshed
+    last_refresh: Instant,
+    /// when the mouse wheel last moved the server selection, if the selected server's jobs
+    /// haven't been fetched since
+    server_wheeled: Option<Instant>,
+    /// when the mouse wheel last moved the build selection, if the selected build's logs
+    /// haven't been fetched since
+    job_wheeled: Option<Instant>,
+    /// build to select once the selected server's jobs have been fetched
+    pending_build: Option<Url>,
+    /// servers whose jobs are being fetched in the background
+    fetching_jobs: BTreeSet<String>,
+    /// servers that failed to refresh so far, while every server's jobs are being refreshed
+    refresh_all_failed: Option<Vec<String>>,
+    /// build whose logs are being fetched in the background, if there is one
+    fetching_logs: Option<Url>,
+    /// which logs are kept in memory
+    log_store: LogStore,
 }
 
 impl Default for App {
     fn default() -> Self {
         Self {
             running: true,
-            status: "ESC, CTRL+C, or q to exit app".to_string(),
+            status: "ESC, CTRL+C, or q to exit app; ? for help, : for commands".to_string(),
             active_pane: 1,
-            jenkins_config_path: PathBuf::new(),
+            config_source: ConfigSource::default(),
+            config: AppConfig::default(),
+            http_mode: HttpMode::default(),
             servers: StatefulServers::default(),
-            jobs: HashMap::new(),
-            log_scroll_state: ScrollViewState::new(),
+            log_scroll_state: LogScrollState::new(),
             wrap_logs: false,
+            layout: PaneLayout::default(),
+            zoomed: false,
+            areas: PaneAreas::default(),
+            dragging: None,
+            line_numbers: false,
+            keymap: Keymap::default(),
+            theme: Theme::default(),
+            job_sort: JobSort::default(),
+            pending_keys: vec![],
+            prompt: None,
+            search: None,
+            help_scroll: None,
+            palette: None,
+            confirm_trigger: None,
+            problem_patterns: RegexSet::empty(),
+            failure_causes: FailureCauses::default(),
+            diff_normalize: vec![],
+            marked: None,
+            diffing: false,
+            diff: None,
+            fold_options: FoldOptions::default(),
+            folds: Folds::default(),
+            folded: None,
+            show_problems: false,
+            keep_log_scroll: false,
+            last_refresh: Instant::now(),
+            server_wheeled: None,
+            job_wheeled: None,
+            pending_build: None,
+            fetching_jobs: BTreeSet::new(),
+            refresh_all_failed: None,
+            fetching_logs: None,
+            log_store: LogStore::default(),
         }
     }
 }
 
 impl App {
-    pub fn new<P: AsRef<Path>>(jenkins_config_path: P) -> Self {
+    pub fn new(config_source: ConfigSource, http_mode: HttpMode) -> Self {
         let mut returnable = Self {
-            jenkins_config_path: jenkins_config_path.as_ref().into(),
+            config_source,
+            http_mode,
             ..Default::default()
         };
         // TODO: move to async?
         returnable.refresh_servers();
+        returnable.active_pane = returnable.config.default_pane;
+        returnable.wrap_logs = returnable.config.wrap_logs;
+        returnable.line_numbers = returnable.config.logs.line_numbers;
+        returnable.fold_options.hide_pipeline = returnable.config.logs.hide_pipeline;
+        returnable.fold_options.timestamps = returnable.config.logs.timestamps;
+        returnable.fold_options.ascii = returnable.config.theme.ascii;
+        returnable.layout = returnable.config.layout.pane_layout();
         returnable
     }
 
@@ -77,64 +458,1636 @@ impl App {
         self.status = status.to_string();
     }
 
-    /// sets the active pane
+    /// sets the active pane. the server list is skipped over while it's hidden.
     pub fn set_active_pane(&mut self, active_pane: i8) {
-        let active_pane = ((active_pane - 1).rem_euclid(3)) + 1;
+        let mut active_pane = ((active_pane - 1).rem_euclid(3)) + 1;
+        if active_pane == 1 && self.servers_hidden() {
+            // coming back around from the logs, or going back from the job list
+            active_pane = if self.active_pane == 3 { 2 } else { 3 };
+        }
         self.status = format!("Setting active pane to {}", active_pane);
         self.active_pane = active_pane;
     }
 
-    /// read the JJB config file from disk to configure the servers available to the GUI
+    /// whether the server list is hidden, since there's only one server to pick from
+    pub fn servers_hidden(&self) -> bool {
+        self.config.layout.hide_single_server && self.servers.servers.len() == 1
+    }
+
+    /// select the only server (and move off the server list) if the server list is hidden,
+    /// since it can't be picked there
+    pub fn select_only_server(&mut self) -> Option<Event> {
+        if !self.servers_hidden() {
+            return None;
+        }
+        if self.active_pane == 1 {
+            self.active_pane = 2;
+        }
+        if self.servers.server_state.selected().is_some() {
+            return None;
+        }
+        self.servers.server_state.select(Some(0));
+        Some(Event::RefreshJobsForServer)
+    }
+
+    /// show just the active pane, or go back to showing them all
+    pub fn toggle_zoom(&mut self) {
+        self.zoomed = !self.zoomed;
+        self.set_status(&if self.zoomed {
+            format!("Zoomed in on pane {}", self.active_pane)
+        } else {
+            "Zoomed out".to_string()
+        });
+    }
+
+    /// make the active pane bigger, or smaller if `grow` is false
+    pub fn resize_pane(&mut self, grow: bool) {
+        if self.zoomed {
+            return;
+        }
+        let step = if grow { RESIZE_STEP } else { -RESIZE_STEP };
+        self.layout.resize(self.active_pane, step);
+    }
+
+    /// switch to the next layout preset
+    pub fn cycle_layout(&mut self) {
+        self.layout.preset = self.layout.preset.next();
+        self.layout.clamp();
+        self.set_status(&format!("Using the {} layout", self.layout.preset));
+    }
+
+    /// the pane at a point on screen, if there is one
+    pub fn pane_at(&self, column: u16, row: u16) -> Option<i8> {
+        let position = (column, row).into();
+        [
+            self.areas.server_list,
+            self.areas.job_list,
+            self.areas.job_logs,
+        ]
+        .iter()
+        .position(|area| area.contains(position))
+        .map(|idx| idx as i8 + 1)
+    }
+
+    /// the border between panes at a point on screen, if there is one. a zoomed pane has none.
+    pub fn border_at(&self, column: u16, row: u16) -> Option<PaneBorder> {
+        if self.zoomed {
+            return None;
+        }
+        self.layout.border_at(&self.areas, column, row)
+    }
+
+    /// move `border` to a point on screen
+    pub fn drag_border(&mut self, border: PaneBorder, column: u16, row: u16) {
+        self.layout.drag_border(&self.areas, border, column, row);
+    }
+
+    /// read the app config and JJB config files from disk to configure the servers available to
+    /// the GUI
     pub fn refresh_servers(&mut self) {
-        self.set_status("Reading JJB config file for servers");
-        self.servers.servers = read_jenkins_config_file(&self.jenkins_config_path).unwrap();
-        self.servers.server_state.select(None);
-        self.set_status(&format!("Found {} servers", self.servers.servers.len()));
-    }
-
-    /// return jobs associated with the selected jenkins instance, if one is selected
-    pub fn get_current_server_jobs(&mut self) -> Option<(&mut StatefulJobs, &mut JenkinsServer)> {
-        if let Some(idx) = self.servers.server_state.selected() {
-            return Some((
-                self.jobs.get_mut(&idx)?,
-                self.servers.servers.values_mut().nth(idx).unwrap(),
+        self.set_status("Reading config files for servers");
+        // everything's worked out before any of it is applied, so a mistake anywhere leaves the
+        // old config in place rather than half of the new one
+        let loaded = self.config_source.load().and_then(|app_config| {
+            let config = app_config.load_servers(self.config_source.section.as_deref())?;
+            let problem_patterns = app_config.problems.regex_set()?;
+            let failure_causes = app_config.failure_causes()?;
+            let diff_normalize = app_config.diff.regexes()?;
+            let keymap = app_config.keymap()?;
+            let theme = app_config.theme()?;
+            Ok((
+                app_config,
+                config,
+                (
+                    problem_patterns,
+                    failure_causes,
+                    diff_normalize,
+                    keymap,
+                    theme,
+                ),
+            ))
+        });
+        let config = match loaded {
+            Ok((app_config, config, settings)) => {
+                (
+                    self.problem_patterns,
+                    self.failure_causes,
+                    self.diff_normalize,
+                    self.keymap,
+                    self.theme,
+                ) = settings;
+                self.config = app_config;
+                config
+            }
+            Err(e) => {
+                self.set_status(&e.to_string());
+                return;
+            }
+        };
+        // keep what's been fetched from servers that are still there, selecting the same one
+        let selected = self.servers.selected_name().map(str::to_string);
+        let all_servers = self.all_servers_selected();
+        let mut old = std::mem::take(&mut self.servers.servers);
+        self.servers.servers = config
+            .servers
+            .into_iter()
+            .map(|(name, mut server)| {
+                server.set_http_mode(self.http_mode.clone());
+                // a section pointed somewhere else is a different server as far as jobs go
+                let jobs = old
+                    .remove(&name)
+                    .filter(|old| old.server.url() == server.url())
+                    .and_then(|old| old.jobs);
+                (name, StatefulServer { server, jobs })
+            })
+            .collect();
+        let selected = if all_servers {
+            self.all_servers_idx()
+        } else {
+            selected.and_then(|name| self.servers.position(&name))
+        };
+        self.servers.server_state.select(selected);
+
+        // show the last known jobs until they're refreshed
+        if let Some(cache) = self.config.cache() {
+            for entry in self.servers.servers.values_mut() {
+                if entry.jobs.is_some() {
+                    continue;
+                }
+                if let Some(cached) = cache.read_jobs(&entry.server) {
+                    let mut stateful_jobs = StatefulJobs {
+                        jobs: cached.jobs,
+                        job_state: TableState::default(),
+                        stale_since: Some(cached.fetched_at),
+                    };
+                    stateful_jobs.sort(self.job_sort);
+                    entry.jobs = Some(stateful_jobs);
+                }
+            }
+        }
+        self.merge_all_servers(None);
+
+        let mut status = format!("Found {} servers", self.servers.servers.len());
+        if !config.skipped.is_empty() {
+            let skipped: Vec<String> = config
+                .skipped
+                .iter()
+                .map(|(name, reason)| format!("[{}] ({})", name, reason))
+                .collect();
+            status.push_str(&format!("; skipped {}", skipped.join(", ")));
+        }
+        let insecure: Vec<&String> = self
+            .servers
+            .servers
+            .iter()
+            .filter(|(_, entry)| entry.server.insecure_skip_verify)
+            .map(|(name, _)| name)
+            .collect();
+        if !insecure.is_empty() {
+            status = format!(
+                "WARNING: TLS certificates are NOT verified for {:?}! {}",
+                insecure, status
+            );
+        }
+        self.set_status(&status);
+    }
+
+    /// move the server selection with the mouse wheel. the selected server's jobs are fetched
+    /// once it settles, rather than for every server scrolled past.
+    pub fn wheel_servers(&mut self, down: bool) {
+        let state = &mut self.servers.server_state;
+        if down {
+            state.select_next();
+        } else {
+            state.select_previous();
+        }
+        self.server_wheeled = Some(Instant::now());
+    }
+
+    /// whether the mouse wheel moved the server selection and its jobs are yet to be fetched
+    pub fn server_wheeled(&self) -> bool {
+        self.server_wheeled.is_some()
+    }
+
+    /// move the build selection with the mouse wheel. the selected build's logs are fetched
+    /// once it settles, rather than for every build scrolled past.
+    pub fn wheel_jobs(&mut self, down: bool) {
+        let Some(stateful_jobs) = self.get_current_server_jobs() else {
+            return;
+        };
+        if down {
+            stateful_jobs.job_state.select_next();
+        } else {
+            stateful_jobs.job_state.select_previous();
+        }
+        self.job_wheeled = Some(Instant::now());
+    }
+
+    /// whether the mouse wheel moved the build selection and its logs are yet to be fetched
+    pub fn job_wheeled(&self) -> bool {
+        self.job_wheeled.is_some()
+    }
+
+    /// where the all-servers view is in the server list, if there is one
+    pub fn all_servers_idx(&self) -> Option<usize> {
+        self.servers.all_servers_idx()
+    }
+
+    /// whether the all-servers view is selected
+    pub fn all_servers_selected(&self) -> bool {
+        self.servers.all_servers_selected()
+    }
+
+    /// how many entries the server list has, counting the all-servers view
+    pub fn server_list_len(&self) -> usize {
+        self.all_servers_idx()
+            .map_or(self.servers.servers.len(), |idx| idx + 1)
+    }
+
+    /// return jobs associated with the selected jenkins instance (or every instance, in the
+    /// all-servers view), if one is selected
+    pub fn get_current_server_jobs(&mut self) -> Option<&mut StatefulJobs> {
+        self.servers.selected_jobs_mut()
+    }
+
+    /// name of the server `job` is from: the selected one, or in the all-servers view, the one
+    /// it was merged from
+    fn server_of<'a>(&'a self, job: &'a JenkinsResult) -> Option<&'a str> {
+        if self.all_servers_selected() {
+            job.server.as_deref()
+        } else {
+            self.servers.selected_name()
+        }
+    }
+
+    /// link of the selected build, if there is one
+    pub fn selected_build(&mut self) -> Option<Url> {
+        self.get_current_server_jobs()?.selected_build()
+    }
+
+    /// refresh jobs for just the instance that's selected (or all of them, in the all-servers
+    /// view) in the background. the selected build stays selected, even if it moved in the list.
+    pub fn refresh_jobs(&mut self, events: &EventSender) {
+        self.server_wheeled = None;
+        self.autosave_session();
+        if self.all_servers_selected() {
+            self.refresh_all_jobs(events);
+        } else if let Some(name) = self.servers.selected_name().map(str::to_string) {
+            self.spawn_job_fetch(name, events);
+        }
+    }
+
+    /// refresh every server's jobs at once in the background
+    pub fn refresh_all_jobs(&mut self, events: &EventSender) {
+        self.refresh_all_failed = Some(vec![]);
+        let names: Vec<String> = self.servers.servers.keys().cloned().collect();
+        for name in names {
+            self.spawn_job_fetch(name, events);
+        }
+        self.set_status(&format!(
+            "Refreshing jobs for {} servers",
+            self.servers.servers.len()
+        ));
+    }
+
+    /// fetch the jobs of the server called `name` on a copy of it, unless they're already being
+    /// fetched, and send them back as an event
+    fn spawn_job_fetch(&mut self, name: String, events: &EventSender) {
+        if !self.fetching_jobs.insert(name.clone()) {
+            return;
+        }
+        let mut jenkins_server = self.servers.servers[&name].server.clone();
+        let events = events.clone();
+        tokio::spawn(async move {
+            let jobs = fetch_server_jobs(&mut jenkins_server)
+                .await
+                .map_err(|e| e.to_string());
+            let fetched = FetchedJobs {
+                server: name,
+                jenkins_server,
+                jobs,
+            };
+            // nothing's listening if the app has quit
+            let _ = events.send(Event::JobsFetched(Box::new(fetched)));
+        });
+    }
+
+    /// take on jobs fetched in the background. returns an event to fetch the selected build's
+    /// logs if the build waiting to be selected just was.
+    pub fn jobs_fetched(&mut self, fetched: FetchedJobs) -> Option<Event> {
+        let FetchedJobs {
+            server: name,
+            jenkins_server,
+            jobs,
+        } = fetched;
+        self.fetching_jobs.remove(&name);
+        let mut event = None;
+        // the server may have been removed, or pointed somewhere else, while they were fetched
+        let status = match self
+            .servers
+            .servers
+            .get_mut(&name)
+            .filter(|entry| entry.server.url() == jenkins_server.url())
+        {
+            Some(entry) => {
+                let timings_failed = jenkins_server
+                    .timings_unavailable
+                    .clone()
+                    .filter(|_| entry.server.timings_unavailable.is_none());
+                entry.server.catch_up(&jenkins_server);
+                match jobs {
+                    Ok(jobs) => {
+                        let selected = self.servers.selected_name() == Some(name.as_str());
+                        let own_build = self.servers.servers[&name]
+                            .jobs
+                            .as_ref()
+                            .and_then(StatefulJobs::selected_build);
+                        // the build waiting to be selected, if nothing else is. it stops
+                        // waiting either way once the selected server's jobs are in
+                        let waiting = if selected {
+                            self.pending_build.take()
+                        } else {
+                            None
+                        };
+                        let pending = own_build.is_none() && waiting.is_some();
+                        let build = if pending { waiting } else { own_build };
+                        let mut status = self.set_server_jobs(&name, jobs, build);
+                        if let Some(e) = timings_failed {
+                            status.push_str(&format!("; no build timings from it: {}", e));
+                        }
+                        if pending && self.selected_job().is_some() {
+                            event = Some(Event::RefreshLogsForJob);
+                        }
+                        Some(status)
+                    }
+                    Err(e) => {
+                        if let Some(failed) = &mut self.refresh_all_failed {
+                            failed.push(format!("[{}] ({})", name, e));
+                        }
+                        Some(format!("Failed to refresh jobs from [{}]: {}", name, e))
+                    }
+                }
+            }
+            None => None,
+        };
+        // a build waiting to be selected in the all-servers view may be from any server, so it
+        // only stops waiting once it's found
+        let pending = self
+            .pending_build
+            .clone()
+            .filter(|_| self.all_servers_selected());
+        self.merge_all_servers(pending.clone());
+        if pending.is_some() && self.selected_job().is_some() {
+            self.pending_build = None;
+            event = Some(Event::RefreshLogsForJob);
+        }
+        // pick up the end of the selected build's logs if it just finished
+        if self.selected_job().is_some_and(|job| job.logs_unfinished) {
+            event = Some(Event::RefreshLogsForJob);
+        }
+
+        if self.fetching_jobs.is_empty() {
+            if let Some(failed) = self.refresh_all_failed.take() {
+                let mut status =
+                    format!("Refreshed jobs for {} servers", self.servers.servers.len());
+                if !failed.is_empty() {
+                    status.push_str(&format!("; failed for {}", failed.join(", ")));
+                }
+                self.set_status(&status);
+                return event;
+            }
+        }
+        if let Some(status) = status.filter(|_| self.refresh_all_failed.is_none()) {
+            self.set_status(&status);
+        }
+        event
+    }
+
+    /// replace the jobs of the server called `name` with freshly fetched ones, then select
+    /// `selected_build` if it's still there. returns what happened, for the status.
+    fn set_server_jobs(
+        &mut self,
+        name: &str,
+        mut jobs: Vec<JenkinsResult>,
+        selected_build: Option<Url>,
+    ) -> String {
+        let entry = self.servers.servers.get_mut(name).unwrap();
+        let job_entry = entry.jobs.get_or_insert_with(StatefulJobs::default);
+        for job in &mut jobs {
+            // causes only get found when the logs are opened, so don't lose them
+            if let Some(old) = job_entry.jobs.iter_mut().find(|old| old.link == job.link) {
+                job.causes = std::mem::take(&mut old.causes);
+                job.timing = job.timing.or(old.timing);
+                job.take_logs(old);
+            }
+        }
+        job_entry.jobs = jobs;
+        job_entry.sort(self.job_sort);
+        job_entry.select_build(selected_build);
+        job_entry.stale_since = None;
+        let mut status = format!(
+            "Fetched {} job results from {}",
+            job_entry.jobs.len(),
+            entry.server
+        );
+        if let Some(cache) = self.config.cache() {
+            if let Err(e) = cache.write_jobs(&entry.server, &job_entry.jobs) {
+                status.push_str(&format!(" (failed to cache them: {})", e));
+            }
+        }
+        status
+    }
+
+    /// gather every server's builds into the all-servers view, keeping its selected build (or
+    /// selecting `selected_build`) and whatever logs it's already loaded
+    fn merge_all_servers(&mut self, selected_build: Option<Url>) {
+        if self.all_servers_idx().is_none() {
+            self.servers.all_servers = None;
+            return;
+        }
+        let mut merged = vec![];
+        let mut stale_since = None;
+        for (name, entry) in &self.servers.servers {
+            let Some(stateful_jobs) = &entry.jobs else {
+                continue;
+            };
+            // it's only as fresh as the stalest server
+            stale_since = stale_since
+                .into_iter()
+                .chain(stateful_jobs.stale_since)
+                .min();
+            merged.extend(stateful_jobs.jobs.iter().map(|job| JenkinsResult {
+                name: job.name.clone(),
+                build_state: job.build_state.clone(),
+                link: job.link.clone(),
+                logs: Log::default(),
+                causes: job.causes.clone(),
+                server: Some(name.clone()),
+                logs_unfinished: false,
+                ..*job
+            }));
+        }
+        let all_servers = self
+            .servers
+            .all_servers
+            .get_or_insert_with(StatefulJobs::default);
+        let selected_link = all_servers.selected_build();
+        for job in &mut merged {
+            if let Some(old) = all_servers.jobs.iter_mut().find(|old| old.link == job.link) {
+                job.take_logs(old);
+            }
+        }
+        all_servers.jobs = merged;
+        all_servers.stale_since = stale_since;
+        all_servers.job_state.select(None);
+        all_servers.sort(self.job_sort);
+        all_servers.select_build(selected_build.or(selected_link));
+    }
+
+    /// refresh job logs for just the job that's selected. finished builds' logs are only fetched
+    /// if they aren't already in memory, spilled to disk, or cached; running builds' logs are
+    /// picked up from where they left off. if `full`, the whole log is fetched from scratch no
+    /// matter how big it is. fetching happens in the background.
+    pub fn refresh_logs(&mut self, full: bool, events: &EventSender) {
+        self.job_wheeled = None;
+        let cache = self.config.cache();
+        let Some(name) = self
+            .selected_job()
+            .and_then(|job| self.server_of(job))
+            .map(str::to_string)
+        else {
+            return;
+        };
+        // a build that's been selected replaces any still waiting to be
+        self.pending_build = None;
+        self.autosave_session();
+        let limit = self.config.logs.limit();
+        let Some((selected_job, jenkins_server)) = self.servers.selected_job_mut() else {
+            return;
+        };
+
+        // a build that finished since its logs were fetched gets one last look for the rest
+        let running =
+            selected_job.build_state == BuildState::Unknown || selected_job.logs_unfinished;
+        let in_memory = !selected_job.logs.is_empty() && !selected_job.logs.is_truncated();
+        let fetch = if full {
+            LogFetch::Whole(LogLimit::Full)
+        } else if in_memory && running {
+            LogFetch::Since(selected_job.logs.fetched_bytes())
+        } else if !selected_job.logs.is_empty() {
+            let status = format!("Showing logs for {}", selected_job);
+            self.logs_loaded(&name, status, false, false, true, events);
+            return;
+        } else if let Some(logs) = self.log_store.unspill(selected_job) {
+            selected_job.logs = logs;
+            let status = format!("Loaded spilled logs for {}", selected_job);
+            self.logs_loaded(&name, status, false, true, true, events);
+            return;
+        } else if let Some(logs) = cache
+            .as_ref()
+            .and_then(|cache| cache.read_log(jenkins_server, selected_job))
+        {
+            // finished builds never change, so there's no point fetching them again
+            selected_job.logs = logs.into();
+            let status = format!("Loaded cached logs for {}", selected_job);
+            self.logs_loaded(&name, status, false, true, true, events);
+            return;
+        } else {
+            LogFetch::Whole(limit)
+        };
+
+        let link = selected_job.link.clone();
+        if self.fetching_logs.as_ref() == Some(&link) {
+            return;
+        }
+        self.fetching_logs = Some(link.clone());
+        let mut jenkins_server = jenkins_server.clone();
+        let events = events.clone();
+        tokio::spawn(async move {
+            let logs = match fetch {
+                LogFetch::Whole(limit) => fetch_logs(&mut jenkins_server, &link, limit)
+                    .await
+                    .map(FetchedLog::Whole),
+                LogFetch::Since(start) => fetch_new_logs(&mut jenkins_server, &link, start)
+                    .await
+                    .map(|(text, next_start)| FetchedLog::Appended {
+                        start,
+                        text,
+                        next_start,
+                    }),
+            }
+            .map_err(|e| e.to_string());
+            let fetched = FetchedLogs {
+                server: name,
+                jenkins_server,
+                link,
+                full,
+                logs,
+            };
+            let _ = events.send(Event::LogsFetched(Box::new(fetched)));
+        });
+    }
+
+    /// take on logs fetched in the background, if they're for the build that's still selected
+    pub fn logs_fetched(&mut self, fetched: FetchedLogs, events: &EventSender) {
+        let FetchedLogs {
+            server: name,
+            jenkins_server,
+            link,
+            full,
+            logs,
+        } = fetched;
+        if self.fetching_logs.as_ref() == Some(&link) {
+            self.fetching_logs = None;
+        }
+        if let Some(entry) = self
+            .servers
+            .servers
+            .get_mut(&name)
+            .filter(|entry| entry.server.url() == jenkins_server.url())
+        {
+            entry.server.catch_up(&jenkins_server);
+        }
+        let logs = match logs {
+            Ok(logs) => logs,
+            Err(e) => {
+                let verb = if full { "load" } else { "refresh" };
+                self.set_status(&format!("Failed to {} logs: {}", verb, e));
+                return;
+            }
+        };
+        let Some((selected_job, _)) = self
+            .servers
+            .selected_job_mut()
+            .filter(|(job, _)| job.link == link)
+        else {
+            return;
+        };
+        let (status, opening) = match logs {
+            FetchedLog::Whole(logs) => {
+                selected_job.logs = logs;
+                let all = if full { "all " } else { "" };
+                (format!("Fetched {}logs for {}", all, selected_job), true)
+            }
+            FetchedLog::Appended {
+                start,
+                text,
+                next_start,
+            } => {
+                // they were loaded some other way in the meantime
+                if selected_job.logs.fetched_bytes() != start {
+                    return;
+                }
+                selected_job.logs.append(&text, next_start);
+                (format!("Fetched new logs for {}", selected_job), false)
+            }
+        };
+        selected_job.logs_unfinished = false;
+        self.logs_loaded(&name, status, true, true, opening, events);
+    }
+
+    /// finish up once the selected build's logs are in, from the server called `name` if they
+    /// were `fetched`: cache them, look for problems and (if they were just `loaded`) failure
+    /// causes in them, and only keep so many in memory. `opening` them, rather than just
+    /// picking up where they left off, jumps to the first problem.
+    fn logs_loaded(
+        &mut self,
+        name: &str,
+        mut status: String,
+        fetched: bool,
+        loaded: bool,
+        opening: bool,
+        events: &EventSender,
+    ) {
+        let cache = self.config.cache();
+        let all_servers = self.all_servers_selected();
+        let Some((selected_job, jenkins_server)) = self.servers.selected_job_mut() else {
+            return;
+        };
+        if let Some(cache) = cache.as_ref().filter(|_| fetched) {
+            if let Err(e) = cache.write_log(jenkins_server, selected_job) {
+                status.push_str(&format!(" (failed to cache them: {})", e));
+            }
+        }
+
+        selected_job.logs.scan_problems(&self.problem_patterns);
+        let mut causes_changed = false;
+        if loaded && !self.failure_causes.is_empty() {
+            let causes = self.failure_causes.analyze(&selected_job.logs);
+            causes_changed = causes != selected_job.causes;
+            selected_job.causes = causes;
+        }
+        let problems = selected_job.logs.problems();
+        if !problems.is_empty() {
+            status.push_str(&format!("; {} problems", problems.len()));
+        }
+
+        let link = selected_job.link.clone();
+        let causes = selected_job.causes.clone();
+        let entry = self.servers.servers.get_mut(name).unwrap();
+        // in the all-servers view, the server's own list needs the causes too
+        if causes_changed && all_servers {
+            if let Some(job) = entry.jobs.as_mut().and_then(|stateful_jobs| {
+                stateful_jobs.jobs.iter_mut().find(|job| job.link == link)
+            }) {
+                job.causes = causes;
+            }
+        }
+        // remember the causes for next time
+        if let (Some(cache), Some(stateful_jobs)) = (cache.as_ref(), entry.jobs.as_ref()) {
+            if causes_changed && stateful_jobs.stale_since.is_none() {
+                if let Err(e) = cache.write_jobs(&entry.server, &stateful_jobs.jobs) {
+                    status.push_str(&format!(" (failed to cache causes: {})", e));
+                }
+            }
+        }
+
+        // only keep so many logs in memory
+        let evicted =
+            self.log_store
+                .touch(&link, self.config.logs.max_in_memory, self.marked.as_ref());
+        for job in self.servers.all_jobs_mut().flat_map(|j| j.jobs.iter_mut()) {
+            if evicted.contains(&job.link) {
+                if let Err(e) = self
+                    .log_store
+                    .evict(job, self.config.logs.spill_threshold())
+                {
+                    status.push_str(&format!(" (failed to spill logs for {}: {})", job, e));
+                }
+            }
+        }
+        self.set_status(&status);
+        self.refold(!opening);
+        let keep_log_scroll = std::mem::take(&mut self.keep_log_scroll);
+        if opening && self.config.problems.jump_to_first && !keep_log_scroll {
+            if let Some(logs) = shown_logs(&self.servers, &self.folded) {
+                if let Some(first) = logs.problems().first() {
+                    self.log_scroll_state.scroll_to_line(*first, self.wrap_logs);
+                }
+            }
+        }
+        // keep comparing against whichever build is selected now
+        if self.diffing {
+            self.diff_with_marked(events);
+        }
+    }
+
+    /// the selected build, if there is one
+    fn selected_job(&self) -> Option<&JenkinsResult> {
+        let stateful_jobs = self.servers.selected_jobs()?;
+        stateful_jobs.jobs.get(stateful_jobs.job_state.selected()?)
+    }
+
+    /// how the selected build's logs are shown, if they're folded
+    fn selected_folded(&self) -> Option<&FoldedLog> {
+        folded_for(&self.folded, &self.selected_job()?.link)
+    }
+
+    /// mark the selected build to compare others against, or unmark it if it's already marked
+    pub fn toggle_mark(&mut self) {
+        let Some(job) = self.selected_job() else {
+            return;
+        };
+        let status = if self.marked.as_ref() == Some(&job.link) {
+            let status = format!("Unmarked {}", job);
+            self.marked = None;
+            self.diffing = false;
+            self.diff = None;
+            status
+        } else if job.logs.is_empty() {
+            format!("Logs for {} haven't loaded yet, so it can't be marked", job)
+        } else {
+            let status = format!("Marked {}; select another build and press d to diff", job);
+            self.marked = Some(job.link.clone());
+            status
+        };
+        self.set_status(&status);
+    }
+
+    /// the marked build, if there is one, from whichever list has its logs
+    pub fn marked_build(&self) -> Option<&JenkinsResult> {
+        self.servers.find_build(self.marked.as_ref()?)
+    }
+
+    /// hide the diff between the marked build and the selected one, or return an event to
+    /// show it
+    pub fn toggle_diff(&mut self) -> Option<Event> {
+        if self.diffing {
+            self.diffing = false;
+            self.diff = None;
+            self.set_status("Closed diff");
+            None
+        } else {
+            Some(Event::DiffLogs)
+        }
+    }
+
+    /// diff the marked build's logs against the selected build's in the background, sending
+    /// the diff back as an event. only the two logs' text is copied for it; the diff itself
+    /// can take a couple of seconds.
+    pub fn diff_with_marked(&mut self, events: &EventSender) {
+        self.diff = None;
+        self.diffing = false;
+        let status = match (self.marked_build(), self.selected_job()) {
+            (None, _) if self.marked.is_some() => {
+                "The marked build isn't listed anymore; mark another with m".to_string()
+            }
+            (None, _) => "Mark a build with m to diff against first".to_string(),
+            (Some(_), None) => "Select a build to diff against".to_string(),
+            (Some(marked), Some(job)) if marked.link == job.link => {
+                format!("{} is the marked build; select another to diff", job)
+            }
+            (Some(marked), _) if marked.logs.is_empty() => {
+                format!(
+                    "Logs for {} aren't loaded anymore; select it to load them",
+                    marked
+                )
+            }
+            (Some(_), Some(job)) if job.logs.is_empty() => {
+                format!("Logs for {} haven't loaded yet", job)
+            }
+            (Some(marked), Some(job)) => {
+                let status = format!("Diffing {} against {}", job, marked);
+                let (left, right) = (
+                    Log::from(marked.logs.text().to_string()),
+                    Log::from(job.logs.text().to_string()),
+                );
+                let (marked, selected) = (marked.link.clone(), job.link.clone());
+                let normalize = self.diff_normalize.clone();
+                let events = events.clone();
+                tokio::task::spawn_blocking(move || {
+                    let diff = BuildDiff {
+                        marked,
+                        selected,
+                        diff: LogDiff::new(&left, &right, &normalize),
+                    };
+                    // nothing's listening if the app has quit
+                    let _ = events.send(Event::DiffComputed(Box::new(diff)));
+                });
+                self.diffing = true;
+                status
+            }
+        };
+        self.set_status(&status);
+    }
+
+    /// show a diff worked out in the background, if it's still for the marked and selected
+    /// builds
+    pub fn diff_computed(&mut self, diff: BuildDiff) {
+        let current = match (self.marked_build(), self.selected_job()) {
+            (Some(marked), Some(job))
+                if marked.link == diff.marked && job.link == diff.selected =>
+            {
+                Some(format!(
+                    "{} vs {}: {} changed, {} removed, {} added lines",
+                    marked, job, diff.diff.changed, diff.diff.removed, diff.diff.added
+                ))
+            }
+            _ => None,
+        };
+        let Some(status) = current.filter(|_| self.diffing) else {
+            return;
+        };
+        self.diff = Some(diff);
+        // the cursor and search are for one log, not a diff of two
+        self.log_scroll_state.hide_cursor();
+        self.search = None;
+        self.log_scroll_state.scroll_to_top();
+        self.set_status(&status);
+    }
+
+    /// scroll the diff to its next (or previous) run of changes
+    pub fn jump_to_change(&mut self, forward: bool) {
+        let Some(BuildDiff { diff, .. }) = &self.diff else {
+            return;
+        };
+        let (_, row) = self.log_scroll_state.offset();
+        let change = if forward {
+            diff.next_change(row)
+        } else {
+            diff.previous_change(row)
+        };
+        match change {
+            Some(row) => {
+                let (x, _) = self.log_scroll_state.offset();
+                self.log_scroll_state.set_offset(x, row);
+            }
+            None if forward => self.set_status("No more changes below"),
+            None => self.set_status("No more changes above"),
+        }
+    }
+
+    /// scroll the selected build's logs to its next (or previous) problem
+    pub fn jump_to_problem(&mut self, forward: bool) {
+        let Some(logs) = shown_logs(&self.servers, &self.folded) else {
+            return;
+        };
+        let top = self.log_scroll_state.top_line(logs, self.wrap_logs);
+        let from = self.log_scroll_state.cursor().unwrap_or(top);
+        let problem = if forward {
+            logs.next_problem(from)
+        } else {
+            logs.previous_problem(from)
+        };
+        let status = match problem {
+            Some(line) => {
+                let nth = logs.problems().partition_point(|problem| *problem < line) + 1;
+                let count = logs.problems().len();
+                self.go_to_line(line);
+                format!(
+                    "Problem {} of {} (line {})",
+                    nth,
+                    count,
+                    self.original_line(line) + 1
+                )
+            }
+            None if logs.problems().is_empty() => "No problems in this log".to_string(),
+            None if forward => "No more problems below".to_string(),
+            None => "No more problems above".to_string(),
+        };
+        self.set_status(&status);
+    }
+
+    /// scroll to `line` of the shown logs, moving the cursor there if it's shown
+    fn go_to_line(&mut self, line: usize) {
+        let Some(logs) = shown_logs(&self.servers, &self.folded) else {
+            return;
+        };
+        if self.log_scroll_state.cursor().is_some() {
+            self.log_scroll_state
+                .move_cursor(logs, line, self.wrap_logs);
+        } else {
+            self.log_scroll_state.scroll_to_line(line, self.wrap_logs);
+        }
+    }
+
+    /// the line of the selected build's original logs that shown line `line` came from
+    fn original_line(&self, line: usize) -> usize {
+        self.selected_folded()
+            .map_or(line, |folded| folded.original_line(line))
+    }
+
+    /// the lines of the selected build's original logs that shown lines `shown` came from,
+    /// including any folded or hidden away between them
+    fn original_range(&self, shown: RangeInclusive<usize>) -> RangeInclusive<usize> {
+        let start = self.original_line(*shown.start());
+        let end = match self.selected_folded() {
+            // the last line could be a collapsed block, so run up to the line after it
+            Some(folded) if *shown.end() + 1 < folded.line_count() => {
+                folded.original_line(shown.end() + 1) - 1
+            }
+            Some(_) => self
+                .selected_job()
+                .map_or(*shown.end(), |job| job.logs.line_count().saturating_sub(1)),
+            None => *shown.end(),
+        };
+        start..=end.max(start)
+    }
+
+    /// the original lines that are selected, or on screen if nothing is
+    fn selected_lines(&self) -> Option<RangeInclusive<usize>> {
+        let logs = shown_logs(&self.servers, &self.folded)?;
+        let shown = match self.log_scroll_state.selection() {
+            Some(selection) => selection,
+            None => {
+                let visible = self.log_scroll_state.visible_lines(logs, self.wrap_logs);
+                visible.start..=visible.end.checked_sub(1)?
+            }
+        };
+        Some(self.original_range(shown))
+    }
+
+    /// the line of the selected build's original logs under the cursor, or at the top of the
+    /// pane if there's no cursor
+    fn current_line(&self) -> usize {
+        shown_logs(&self.servers, &self.folded).map_or(0, |logs| {
+            let top = self.log_scroll_state.top_line(logs, self.wrap_logs);
+            self.original_line(self.log_scroll_state.cursor().unwrap_or(top))
+        })
+    }
+
+    /// start selecting lines from the cursor, showing it at the top of the pane if it isn't
+    /// already, or stop selecting
+    pub fn toggle_selection(&mut self) {
+        if self.diff.is_some() {
+            return;
+        }
+        let Some(logs) = shown_logs(&self.servers, &self.folded) else {
+            return;
+        };
+        if self.log_scroll_state.cursor().is_none() {
+            let top = self.log_scroll_state.top_line(logs, self.wrap_logs);
+            self.log_scroll_state.move_cursor(logs, top, self.wrap_logs);
+        }
+        self.log_scroll_state.toggle_selection();
+        if self.log_scroll_state.selection().is_some() {
+            self.set_status(
+                "Selecting lines; y to copy them, s or S to save them, / to search in them",
+            );
+        } else {
+            self.set_status("Stopped selecting lines");
+        }
+    }
+
+    /// move the cursor down (or up, if negative) by `lines`
+    pub fn move_cursor(&mut self, lines: isize) {
+        let Some(logs) = shown_logs(&self.servers, &self.folded) else {
+            return;
+        };
+        if let Some(cursor) = self.log_scroll_state.cursor() {
+            self.log_scroll_state.move_cursor(
+                logs,
+                cursor.saturating_add_signed(lines),
+                self.wrap_logs,
+            );
+        }
+    }
+
+    /// back out of the last thing started in the logs: a search, then a selection, then the
+    /// cursor. returns whether there was anything to back out of.
+    pub fn back_out_of_logs(&mut self) -> bool {
+        if self.search.take().is_some() {
+            self.set_status("Cleared search");
+        } else if self.log_scroll_state.selection().is_some() {
+            self.log_scroll_state.toggle_selection();
+            self.set_status("Stopped selecting lines");
+        } else if self.log_scroll_state.cursor().is_some() {
+            self.log_scroll_state.hide_cursor();
+        } else {
+            return false;
+        }
+        true
+    }
+
+    pub fn toggle_line_numbers(&mut self) {
+        self.line_numbers = !self.line_numbers;
+    }
+
+    /// start typing a search of the selected build's logs
+    pub fn start_search(&mut self) {
+        if self.diff.is_none() && self.selected_job().is_some() {
+            self.prompt = Some(String::new());
+        }
+    }
+
+    /// search the selected build's logs (just the selected lines, if there's a selection) for
+    /// what was typed, and go to the first match from the cursor or the top of the pane. it's
+    /// case insensitive unless there's an uppercase letter in it.
+    pub fn submit_search(&mut self) {
+        let Some(query) = self.prompt.take().filter(|query| !query.is_empty()) else {
+            return;
+        };
+        let pattern = match RegexBuilder::new(&query)
+            .case_insensitive(!query.chars().any(char::is_uppercase))
+            .build()
+        {
+            Ok(pattern) => pattern,
+            Err(e) => {
+                self.set_status(&format!("Invalid search: {}", e));
+                return;
+            }
+        };
+        let within = self
+            .log_scroll_state
+            .selection()
+            .map(|selection| self.original_range(selection));
+        let Some(logs) = shown_logs(&self.servers, &self.folded) else {
+            return;
+        };
+        let mut search = LogSearch::new(pattern, within.clone());
+        search.search(logs, |line| self.original_line(line));
+
+        let top = self.log_scroll_state.top_line(logs, self.wrap_logs);
+        let from = self.log_scroll_state.cursor().unwrap_or(top);
+        let first = search
+            .matches()
+            .iter()
+            .find(|line| **line >= from)
+            .or(search.matches().first())
+            .copied();
+        let mut status = format!("{} matches for {}", search.matches().len(), query);
+        if let Some(within) = within {
+            status.push_str(&format!(
+                " in lines {}-{}",
+                within.start() + 1,
+                within.end() + 1
             ));
         }
-        None
+        if let Some(line) = first {
+            self.go_to_line(line);
+            status.push_str("; n and N go to the next and previous one");
+        }
+        self.search = Some(search);
+        self.set_status(&status);
+    }
+
+    /// go to the next (or previous) match of the search
+    pub fn jump_to_match(&mut self, forward: bool) {
+        let (Some(search), Some(logs)) = (&self.search, shown_logs(&self.servers, &self.folded))
+        else {
+            return;
+        };
+        let top = self.log_scroll_state.top_line(logs, self.wrap_logs);
+        let from = self.log_scroll_state.cursor().unwrap_or(top);
+        let found = if forward {
+            search.next_match(from)
+        } else {
+            search.previous_match(from)
+        };
+        let count = search.matches().len();
+        let status = match found {
+            Some(line) => {
+                let nth = search.matches().partition_point(|m| *m < line) + 1;
+                self.go_to_line(line);
+                format!(
+                    "Match {} of {} (line {})",
+                    nth,
+                    count,
+                    self.original_line(line) + 1
+                )
+            }
+            None if count == 0 => "No matches".to_string(),
+            None if forward => "No more matches below".to_string(),
+            None => "No more matches above".to_string(),
+        };
+        self.set_status(&status);
+    }
+
+    /// save the selected build's logs into the export directory, either as they are or with
+    /// the escape sequences stripped
+    pub fn save_logs(&mut self, stripped: bool) {
+        let Some(job) = self.selected_job().filter(|job| !job.logs.is_empty()) else {
+            return;
+        };
+        // just the selected lines, if there are any
+        let selection = self
+            .log_scroll_state
+            .selection()
+            .map(|selection| self.original_range(selection));
+        let extension = if stripped { "txt" } else { "log" };
+        let (name, text) = match &selection {
+            Some(lines) => (
+                export::lines_file_name(job, lines, extension),
+                format!("{}\n", job.logs.lines_text(lines.clone())),
+            ),
+            None => (
+                export::file_name(job, extension),
+                job.logs.text().to_string(),
+            ),
+        };
+        let contents = if stripped {
+            export::strip_ansi(&text)
+        } else {
+            text
+        };
+        let status = match self
+            .config
+            .export
+            .dir()
+            .and_then(|dir| Ok(export::save(&dir, &name, &contents)?))
+        {
+            Ok(path) => format!("Saved logs for {} to {}", job, path.display()),
+            Err(e) => format!("Failed to save logs for {}: {}", job, e),
+        };
+        self.set_status(&status);
+    }
+
+    /// the selected build's logs, for showing in a pager
+    pub fn logs_for_pager(&self) -> Option<String> {
+        let job = self.selected_job().filter(|job| !job.logs.is_empty())?;
+        Some(export::strip_console_notes(job.logs.text()).into_owned())
+    }
+
+    /// write the selected build's logs to a temp file for an editor, returning it and the line
+    /// to open it at. the file goes away once it's dropped.
+    pub fn logs_for_editor(&self) -> Result<Option<(NamedTempFile, usize)>, Box<dyn Error>> {
+        let Some(job) = self.selected_job().filter(|job| !job.logs.is_empty()) else {
+            return Ok(None);
+        };
+        let file = export::save_temp(job, "log", &export::strip_ansi(job.logs.text()))?;
+        Ok(Some((file, self.current_line() + 1)))
+    }
+
+    /// copy the selected build's link to the clipboard
+    pub fn copy_link(&mut self, link: fn(&JenkinsResult) -> Url) {
+        let Some(link) = self.selected_job().map(link) else {
+            return;
+        };
+        let status = match export::copy_to_clipboard(link.as_str()) {
+            Ok(_) => format!("Copied {}", link),
+            Err(e) => format!("Failed to copy {}: {}", link, e),
+        };
+        self.set_status(&status);
+    }
+
+    /// open the selected build's link in the browser
+    pub fn open_link(&mut self, link: fn(&JenkinsResult) -> Url) {
+        let Some(link) = self.selected_job().map(link) else {
+            return;
+        };
+        let status = match export::open_url(self.config.browser.as_deref(), link.as_str()) {
+            Ok(()) => format!("Opened {}", link),
+            Err(e) => format!("Failed to open {}: {}", link, e),
+        };
+        self.set_status(&status);
+    }
+
+    /// copy the selected build's log lines that are selected (or on screen, if none are), or
+    /// all of them, to the clipboard
+    pub fn copy_logs(&mut self, all: bool) {
+        let Some(job) = self.selected_job().filter(|job| !job.logs.is_empty()) else {
+            return;
+        };
+        let lines = if all {
+            0..=job.logs.line_count() - 1
+        } else {
+            let Some(lines) = self.selected_lines() else {
+                return;
+            };
+            lines
+        };
+        let text = export::strip_ansi(job.logs.lines_text(lines.clone()));
+        let status = match export::copy_to_clipboard(&text) {
+            Ok(copied) if copied < text.len() => format!(
+                "Copied only the first {} of {} bytes of lines {}-{} of {} (terminals won't take more)",
+                copied,
+                text.len(),
+                lines.start() + 1,
+                lines.end() + 1,
+                job
+            ),
+            Ok(_) => format!(
+                "Copied lines {}-{} of {} ({} bytes)",
+                lines.start() + 1,
+                lines.end() + 1,
+                job,
+                text.len()
+            ),
+            Err(e) => format!("Failed to copy logs: {}", e),
+        };
+        self.set_status(&status);
+    }
+
+    /// export the selected server's jobs as CSV, or Markdown, into the export directory
+    pub fn export_jobs(&mut self, markdown: bool) {
+        let Some(stateful_jobs) = self.servers.selected_jobs() else {
+            return;
+        };
+        let name = self.servers.selected_name().unwrap_or("all-servers");
+        let (extension, contents) = if markdown {
+            ("md", export::jobs_markdown(&stateful_jobs.jobs))
+        } else {
+            ("csv", export::jobs_csv(&stateful_jobs.jobs))
+        };
+        let file_name = format!("{}-jobs.{}", export::sanitize(name), extension);
+        let status = match self
+            .config
+            .export
+            .dir()
+            .and_then(|dir| Ok(export::save(&dir, &file_name, &contents)?))
+        {
+            Ok(path) => format!(
+                "Exported {} jobs to {}",
+                stateful_jobs.jobs.len(),
+                path.display()
+            ),
+            Err(e) => format!("Failed to export jobs: {}", e),
+        };
+        self.set_status(&status);
+    }
+
+    /// re-parse the selected build's pipeline blocks and re-render how its logs are shown,
+    /// keeping the same line at the top, and the cursor and search on the same lines, if
+    /// `keep_top`. folds start out expanded for each build.
+    fn refold(&mut self, keep_top: bool) {
+        let Some(job) = self.selected_job() else {
+            self.folded = None;
+            return;
+        };
+        let link = job.link.clone();
+        let same_build = self
+            .folded
+            .as_ref()
+            .is_some_and(|(folded_link, _)| *folded_link == link);
+        let top = shown_logs(&self.servers, &self.folded)
+            .filter(|_| keep_top)
+            .map(|logs| {
+                let top = self.log_scroll_state.top_line(logs, self.wrap_logs);
+                self.selected_folded()
+                    .map_or(top, |folded| folded.original_line(top))
+            });
+
+        if !same_build {
+            self.fold_options.collapsed.clear();
+        }
+        // only the new part of logs that have been added to needs parsing
+        if !same_build || !keep_top {
+            self.folds = Folds::default();
+        }
+        if keep_top {
+            let old = folded_for(&self.folded, &link);
+            self.log_scroll_state
+                .remap_cursor(|line| old.map_or(line, |folded| folded.original_line(line)));
+        } else {
+            self.log_scroll_state.hide_cursor();
+            self.search = None;
+        }
+
+        let stateful_jobs = self.servers.selected_jobs().unwrap();
+        let job = &stateful_jobs.jobs[stateful_jobs.job_state.selected().unwrap()];
+        self.folds.parse(&job.logs);
+        self.folded = FoldedLog::new(&job.logs, &self.folds, &self.fold_options)
+            .map(|folded| (link.clone(), folded));
+
+        let new = folded_for(&self.folded, &link);
+        self.log_scroll_state
+            .remap_cursor(|line| new.map_or(line, |folded| folded.shown_line(line)));
+        if let (Some(search), Some(logs)) =
+            (&mut self.search, shown_logs(&self.servers, &self.folded))
+        {
+            search.search(logs, |line| {
+                new.map_or(line, |folded| folded.original_line(line))
+            });
+        }
+
+        if let Some(top) = top {
+            let shown = self
+                .selected_folded()
+                .map_or(top, |folded| folded.shown_line(top));
+            self.log_scroll_state.scroll_to_line(shown, self.wrap_logs);
+        }
+    }
+
+    /// collapse or expand the innermost pipeline block at the top of the logs
+    pub fn toggle_fold(&mut self) {
+        let Some(logs) = shown_logs(&self.servers, &self.folded) else {
+            return;
+        };
+        let top = self.log_scroll_state.top_line(logs, self.wrap_logs);
+        let top = self
+            .selected_folded()
+            .map_or(top, |folded| folded.original_line(top));
+        let folds = if self.selected_folded().is_some() {
+            self.folds.folds()
+        } else {
+            &[]
+        };
+        let Some(fold) = fold_at(folds, top) else {
+            self.set_status("Not in a pipeline block");
+            return;
+        };
+        let start = fold.start;
+        let status = if self.fold_options.collapsed.remove(&start) {
+            format!("Expanded {}", fold.label)
+        } else {
+            self.fold_options.collapsed.insert(start);
+            format!("Collapsed {}", fold.label)
+        };
+        self.set_status(&status);
+        self.refold(true);
+        // keep the block's first line at the top, rather than whatever came after it
+        if self.selected_job().is_some() {
+            let shown = self
+                .selected_folded()
+                .map_or(start, |folded| folded.shown_line(start));
+            self.log_scroll_state.scroll_to_line(shown, self.wrap_logs);
+        }
+    }
+
+    /// expand every pipeline block if any are collapsed, or collapse the outermost ones
+    pub fn toggle_all_folds(&mut self) {
+        if self.fold_options.collapsed.is_empty() {
+            let mut end = None;
+            for fold in self.folds.folds() {
+                if end.is_none_or(|end| fold.start > end) {
+                    self.fold_options.collapsed.insert(fold.start);
+                    end = Some(fold.end);
+                }
+            }
+            self.set_status("Collapsed all pipeline blocks");
+        } else {
+            self.fold_options.collapsed.clear();
+            self.set_status("Expanded all pipeline blocks");
+        }
+        self.refold(true);
+    }
+
+    /// show or hide `[Pipeline]` bookkeeping lines
+    pub fn toggle_hide_pipeline(&mut self) {
+        self.fold_options.hide_pipeline = !self.fold_options.hide_pipeline;
+        self.set_status(if self.fold_options.hide_pipeline {
+            "Hiding [Pipeline] lines"
+        } else {
+            "Showing [Pipeline] lines"
+        });
+        self.refold(true);
+    }
+
+    /// switch between showing, stripping, and showing relative Timestamper timestamps
+    pub fn cycle_timestamps(&mut self) {
+        self.fold_options.timestamps = self.fold_options.timestamps.next();
+        self.set_status(match self.fold_options.timestamps {
+            TimestampMode::Show => "Showing timestamps",
+            TimestampMode::Strip => "Stripping timestamps",
+            TimestampMode::Relative => "Showing timestamps relative to the start of the build",
+        });
+        self.refold(true);
+    }
+
+    /// sort the jobs by the next column, in its natural order
+    pub fn cycle_job_sort(&mut self) {
+        self.job_sort.column = self.job_sort.column.next();
+        self.job_sort.reversed = false;
+        self.sort_jobs();
+    }
+
+    pub fn reverse_job_sort(&mut self) {
+        self.job_sort.reversed = !self.job_sort.reversed;
+        self.sort_jobs();
     }
 
-    /// refresh jobs for just the instance that's selected
-    pub async fn refresh_jobs(&mut self) -> Result<(), Box<dyn Error>> {
-        if let Some(idx) = self.servers.server_state.selected() {
-            let jenkins_server = self.servers.servers.values_mut().nth(idx).unwrap();
-            let job_entry = self.jobs.entry(idx).or_default();
-            job_entry.jobs = fetch_jenkins_results(jenkins_server).await?.collect();
-            job_entry.job_state.select(None);
-            let updated_job_counter = job_entry.jobs.len();
+    /// sort every server's jobs, and say how
+    fn sort_jobs(&mut self) {
+        for stateful_jobs in self.servers.all_jobs_mut() {
+            stateful_jobs.sort(self.job_sort);
+        }
+        let reversed = if self.job_sort.reversed {
+            ", reversed"
+        } else {
+            ""
+        };
+        self.set_status(&format!(
+            "Sorting jobs by {}{}",
+            self.job_sort.column, reversed
+        ));
+    }
+
+    /// the selected server's jobs, if they've been fetched
+    fn selected_jobs(&self) -> Option<&StatefulJobs> {
+        self.servers.selected_jobs()
+    }
+
+    /// the latest build of the job called `name` on the selected server. in the all-servers
+    /// view, servers running the same jobs have jobs with the same names, so it's the one on the
+    /// selected build's server if that has one, or the one updated last otherwise.
+    fn latest_build(&self, name: &str) -> Option<usize> {
+        let jobs = &self.selected_jobs()?.jobs;
+        let server = self.selected_job().and_then(|job| job.server.as_deref());
+        let named = || (0..jobs.len()).filter(|idx| jobs[*idx].name == name);
+        named()
+            .filter(|idx| jobs[*idx].server.as_deref() == server)
+            .max_by_key(|idx| jobs[*idx].build_number)
+            .or_else(|| named().max_by_key(|idx| jobs[*idx].updated))
+    }
+
+    /// what the command palette suggests for what's been typed into it, completing server names
+    /// and the selected server's job names
+    pub fn palette_suggestions(&self) -> Vec<Suggestion> {
+        let Some(palette) = &self.palette else {
+            return vec![];
+        };
+        let servers: Vec<&str> = self.servers.servers.keys().map(String::as_str).collect();
+        let mut jobs: Vec<&str> = self
+            .selected_jobs()
+            .map(|stateful_jobs| {
+                stateful_jobs
+                    .jobs
+                    .iter()
+                    .map(|job| job.name.as_str())
+                    .collect()
+            })
+            .unwrap_or_default();
+        jobs.sort_unstable();
+        jobs.dedup();
+        palette.suggestions(&servers, &jobs)
+    }
+
+    /// select the server called `name`, returning whether there is one
+    pub fn select_server(&mut self, name: &str) -> bool {
+        let Some(idx) = self.servers.position(name) else {
+            self.set_status(&format!("No server named {}", name));
+            return false;
+        };
+        self.servers.server_state.select(Some(idx));
+        true
+    }
+
+    /// select the latest build of the job called `name`, returning whether there is one
+    pub fn select_job(&mut self, name: &str) -> bool {
+        let Some(idx) = self.latest_build(name) else {
+            self.set_status(&format!("No job named {}", name));
+            return false;
+        };
+        if let Some(stateful_jobs) = self.get_current_server_jobs() {
+            stateful_jobs.job_state.select(Some(idx));
+        }
+        true
+    }
+
+    /// ask whether to start a build of the job called exactly `name` on the selected server, or
+    /// of the selected build's job, since a build can't be taken back once it's started
+    pub fn confirm_trigger_build(&mut self, name: Option<String>) {
+        let job = match &name {
+            Some(name) => self
+                .latest_build(name)
+                .and_then(|idx| self.selected_jobs()?.jobs.get(idx))
+                .ok_or_else(|| format!("No job named {}", name)),
+            None => self.selected_job().ok_or("No build selected".to_string()),
+        };
+        let trigger = job.and_then(|job| {
+            let server = self.server_of(job).ok_or("No server selected")?;
+            Ok(BuildTrigger {
+                name: job.name.clone(),
+                server: server.to_string(),
+                link: job.job_link(),
+            })
+        });
+        match trigger {
+            Ok(trigger) => {
+                self.set_status(&format!(
+                    "Start a build of {} on [{}]? (y/n)",
+                    trigger.name, trigger.server
+                ));
+                self.confirm_trigger = Some(trigger);
+            }
+            Err(e) => self.set_status(&e),
+        }
+    }
+
+    /// start a build the user said yes to. the request is made in the background, so a slow
+    /// server doesn't freeze the UI.
+    pub fn trigger_build(&mut self, trigger: BuildTrigger, events: &EventSender) {
+        let Some(jenkins_server) = self
+            .servers
+            .servers
+            .get(&trigger.server)
+            .map(|entry| entry.server.clone())
+        else {
             self.set_status(&format!(
-                "Fetched {} job results from {}",
-                updated_job_counter,
-                self.servers.servers.values().nth(idx).unwrap()
+                "Failed to trigger a build: no server named {}",
+                trigger.server
             ));
+            return;
+        };
+        self.set_status(&format!("Triggering a build of {}", trigger.name));
+        let events = events.clone();
+        tokio::spawn(async move {
+            let result = jenkins_server
+                .trigger_build(&trigger.link)
+                .await
+                .map_err(|e| e.to_string());
+            // nothing's listening if the app has quit
+            let _ = events.send(Event::BuildTriggered(trigger, result));
+        });
+    }
+
+    /// say how starting a build went
+    pub fn build_triggered(&mut self, trigger: BuildTrigger, result: Result<(), String>) {
+        match result {
+            Ok(()) => self.set_status(&format!("Triggered a build of {}", trigger.name)),
+            Err(e) => self.set_status(&format!(
+                "Failed to trigger a build of {}: {}",
+                trigger.name, e
+            )),
         }
-        Ok(())
     }
 
-    /// refresh job logs for just the job that's selected
-    pub async fn refresh_logs(&mut self) -> Result<(), Box<dyn Error>> {
-        let mut selected_job_name = String::new();
-        if let Some((stateful_jobs, jenkins_server)) = self.get_current_server_jobs() {
-            if let Some(job_idx) = stateful_jobs.job_state.selected() {
-                let selected_job = stateful_jobs.jobs.get_mut(job_idx).unwrap();
-                selected_job.hydrate_logs(jenkins_server).await?;
-                selected_job_name = format!("{}", selected_job);
-            }
+    /// called every tick; returns an event if the selected server's jobs are due for an
+    /// automatic refresh, or the mouse wheel has settled on a server or build to fetch
+    pub fn tick(&mut self) -> Option<Event> {
+        if self
+            .server_wheeled
+            .is_some_and(|wheeled| wheeled.elapsed() >= WHEEL_SETTLE)
+        {
+            return Some(Event::RefreshJobsForServer);
+        }
+        if self
+            .job_wheeled
+            .is_some_and(|wheeled| wheeled.elapsed() >= WHEEL_SETTLE)
+        {
+            return Some(Event::RefreshLogsForJob);
+        }
+        let interval = self.config.refresh_interval()?;
+        if self.last_refresh.elapsed() < interval || self.servers.server_state.selected().is_none()
+        {
+            return None;
+        }
+        self.last_refresh = Instant::now();
+        Some(Event::RefreshJobsForServer)
+    }
+
+    /// snapshot what's selected, to be restored next time
+    pub fn session(&mut self) -> Session {
+        Session {
+            server: self.servers.selected_name().map(str::to_string),
+            all_servers: self.all_servers_selected(),
+            build_link: self.selected_build().or_else(|| self.pending_build.clone()),
+            active_pane: self.active_pane,
+            wrap_logs: self.wrap_logs,
+            log_scroll: self.log_scroll_state.offset(),
+            layout: Some(self.layout),
+        }
+    }
+
+    /// save the session if there's somewhere to save it
+    pub fn save_session(&mut self) -> Result<(), Box<dyn Error>> {
+        if let Some(path) = self.config.session_file() {
+            self.session().save(path)?;
         }
-        self.set_status(&format!("Fetched logs for {}", selected_job_name));
         Ok(())
     }
 
-    pub fn tick(&self) {}
+    /// save the session whenever the selection changes or is refreshed, so it isn't lost if
+    /// the app doesn't quit cleanly
+    fn autosave_session(&mut self) {
+        if let Err(e) = self.save_session() {
+            self.set_status(&format!("Failed to save the session: {}", e));
+        }
+    }
+
+    /// select whatever was selected last time, if session restoring is enabled. returns the
+    /// events needed to fetch the selected server's jobs and the selected build's logs.
+    pub fn restore_session(&mut self) -> Vec<Event> {
+        let mut events = vec![];
+        if !self.config.session.restore {
+            return events;
+        }
+        let Some(session) = self.config.session_file().and_then(Session::load) else {
+            return events;
+        };
+
+        self.active_pane = session.active_pane.clamp(1, 3);
+        self.wrap_logs = session.wrap_logs;
+        if let Some(mut layout) = session.layout {
+            layout.clamp();
+            self.layout = layout;
+        }
+        let server_idx = if session.all_servers {
+            self.all_servers_idx()
+        } else {
+            session.server.and_then(|name| self.servers.position(&name))
+        };
+        let Some(server_idx) = server_idx else {
+            return events;
+        };
+        self.servers.server_state.select(Some(server_idx));
+        events.push(Event::RefreshJobsForServer);
+
+        if let Some(link) = session.build_link {
+            // cached jobs can be selected right away; otherwise wait for the refresh
+            if let Some(stateful_jobs) = self.servers.selected_jobs_mut() {
+                stateful_jobs.select_build(Some(link.clone()));
+            }
+            if self.selected_build().is_none() {
+                self.pending_build = Some(link);
+            }
+            let (x, y) = session.log_scroll;
+            self.log_scroll_state.set_offset(x, y);
+            self.keep_log_scroll = true;
+            events.push(Event::RefreshLogsForJob);
+        }
+        events
+    }
 
     /// set running to false to quit the application.
     pub fn quit(&mut self) {
@@ -0,0 +1,145 @@
+use std::{
+    error::Error,
+    fs::{self, File},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use chrono::{DateTime, Local};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    jenkins::{BuildState, JenkinsResult, JenkinsServer},
+    record::url_file_name,
+};
+
+/// a server's jobs as of the last time they were fetched
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CachedJobs {
+    pub fetched_at: DateTime<Local>,
+    pub jobs: Vec<JenkinsResult>,
+}
+
+/// on-disk cache of each server's jobs and finished builds' logs, so the last known state shows
+/// up immediately on startup and while offline
+#[derive(Debug, Clone)]
+pub struct Cache {
+    dir: PathBuf,
+    /// evict the least recently used logs once they take up more than this many bytes
+    max_size: u64,
+}
+
+impl Cache {
+    pub fn new<P: AsRef<Path>>(dir: P, max_size: u64) -> Self {
+        Self {
+            dir: dir.as_ref().into(),
+            max_size,
+        }
+    }
+
+    /// everything for one server lives under a directory named for its URL, so renaming the
+    /// server in the config doesn't throw the cache away. the name (like each log's) includes
+    /// a hash of the whole URL, so no two servers or builds share a file.
+    fn server_dir(&self, server: &JenkinsServer) -> PathBuf {
+        match Url::parse(server.url()) {
+            Ok(url) => self.dir.join(url_file_name(&url)),
+            Err(_) => self.dir.join("invalid-url"),
+        }
+    }
+
+    fn logs_dir(&self, server: &JenkinsServer) -> PathBuf {
+        self.server_dir(server).join("logs")
+    }
+
+    fn log_path(&self, server: &JenkinsServer, job: &JenkinsResult) -> PathBuf {
+        self.logs_dir(server)
+            .join(format!("{}.log", url_file_name(&job.link)))
+    }
+
+    pub fn read_jobs(&self, server: &JenkinsServer) -> Option<CachedJobs> {
+        let content = fs::read_to_string(self.server_dir(server).join("jobs.json")).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    pub fn write_jobs(
+        &self,
+        server: &JenkinsServer,
+        jobs: &[JenkinsResult],
+    ) -> Result<(), Box<dyn Error>> {
+        let dir = self.server_dir(server);
+        fs::create_dir_all(&dir)?;
+        let cached = CachedJobs {
+            fetched_at: Local::now(),
+            jobs: jobs.to_vec(),
+        };
+        fs::write(dir.join("jobs.json"), serde_json::to_string(&cached)?)?;
+        Ok(())
+    }
+
+    /// the cached log for a finished build, if there is one
+    pub fn read_log(&self, server: &JenkinsServer, job: &JenkinsResult) -> Option<String> {
+        let path = self.log_path(server, job);
+        let logs = fs::read_to_string(&path).ok()?;
+        // bump the modified time so eviction is least-recently-used rather than oldest
+        if let Ok(file) = File::options().write(true).open(&path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+        Some(logs)
+    }
+
+    /// save the log of a finished build. logs of builds that might still be running are never
+    /// cached, since they may still change, and neither are partial logs.
+    pub fn write_log(
+        &self,
+        server: &JenkinsServer,
+        job: &JenkinsResult,
+    ) -> Result<(), Box<dyn Error>> {
+        if job.build_state == BuildState::Unknown
+            || job.logs_unfinished
+            || job.logs.is_empty()
+            || job.logs.is_truncated()
+        {
+            return Ok(());
+        }
+        fs::create_dir_all(self.logs_dir(server))?;
+        fs::write(self.log_path(server, job), job.logs.text())?;
+        self.evict();
+        Ok(())
+    }
+
+    /// remove the least recently used logs until the cache fits in `max_size`. it's only
+    /// housekeeping, so files that can't be looked at or removed (say, because something else
+    /// removed them first) are skipped rather than stopping it.
+    fn evict(&self) {
+        let Ok(server_dirs) = fs::read_dir(&self.dir) else {
+            return;
+        };
+        let mut logs: Vec<(SystemTime, u64, PathBuf)> = vec![];
+        for server_dir in server_dirs.flatten() {
+            let Ok(entries) = fs::read_dir(server_dir.path().join("logs")) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let Ok(metadata) = entry.metadata() else {
+                    continue;
+                };
+                let Ok(modified) = metadata.modified() else {
+                    continue;
+                };
+                logs.push((modified, metadata.len(), entry.path()));
+            }
+        }
+
+        let mut total: u64 = logs.iter().map(|(_, len, _)| len).sum();
+        logs.sort();
+        for (_, len, path) in logs {
+            if total <= self.max_size {
+                break;
+            }
+            if fs::remove_file(path).is_ok() {
+                total -= len;
+            }
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use std::{collections::BTreeMap, error::Error, fmt::Display, fs, path::Path};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cache::Cache,
+    jenkins::{fetch_jenkins_results, JenkinsResult, JenkinsServer},
+    log_store::LogLimit,
+    log_view::Log,
+};
+
+/// a known way for a build to fail, as written in the rules file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CauseRule {
+    name: String,
+    /// regex matched against the whole log; use `(?m)` for `^`/`$` to match lines
+    pattern: String,
+    #[serde(default)]
+    description: String,
+    /// e.g. "infra", "flaky test", "OOM", "dependency download"
+    #[serde(default)]
+    category: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CauseRulesFile {
+    #[serde(default, rename = "cause")]
+    causes: Vec<CauseRule>,
+}
+
+/// a cause that was found in a build's log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FoundCause {
+    pub name: String,
+    pub category: String,
+    pub description: String,
+    /// first line it was found on
+    pub line: usize,
+}
+
+/// the knowledge base of failure causes to look for in logs
+#[derive(Debug, Clone, Default)]
+pub struct FailureCauses {
+    rules: Vec<(CauseRule, Regex)>,
+}
+
+impl FailureCauses {
+    /// read the rules file, as TOML or YAML if the extension says so
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let content = fs::read_to_string(path)?;
+        let file: CauseRulesFile = match path.extension().and_then(|e| e.to_str()) {
+            Some("yaml" | "yml") => serde_yaml::from_str(&content)?,
+            _ => toml::from_str(&content)?,
+        };
+        let rules = file
+            .causes
+            .into_iter()
+            .map(|rule| {
+                let regex = Regex::new(&rule.pattern)
+                    .map_err(|e| format!("invalid pattern for cause {:?}: {}", rule.name, e))?;
+                Ok((rule, regex))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Self { rules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// every cause whose pattern matches somewhere in the log
+    pub fn analyze(&self, log: &Log) -> Vec<FoundCause> {
+        self.rules
+            .iter()
+            .filter_map(|(rule, regex)| {
+                let found = regex.find(log.text())?;
+                Some(FoundCause {
+                    name: rule.name.clone(),
+                    category: rule.category.clone(),
+                    description: rule.description.clone(),
+                    line: log.line_at(found.start()),
+                })
+            })
+            .collect()
+    }
+}
+
+/// how often each cause showed up across many builds
+#[derive(Debug, Default)]
+pub struct Analysis {
+    /// how many builds were analyzed
+    pub builds: usize,
+    /// map of (category, cause name) to the builds it was found in
+    pub causes: BTreeMap<(String, String), Vec<String>>,
+    /// builds that no cause matched
+    pub unknown: Vec<String>,
+    /// builds whose logs couldn't be fetched, and why
+    pub errors: Vec<String>,
+}
+
+impl Analysis {
+    /// analyze the builds on `server` that `include` picks, reusing cached logs when possible
+    pub async fn add_server(
+        &mut self,
+        name: &str,
+        server: &mut JenkinsServer,
+        causes: &FailureCauses,
+        cache: Option<&Cache>,
+        limit: LogLimit,
+        include: impl Fn(&JenkinsResult) -> bool,
+    ) -> Result<(), Box<dyn Error>> {
+        for mut job in fetch_jenkins_results(server).await?.filter(include) {
+            let build = format!("[{}] {}", name, job);
+            match cache.and_then(|cache| cache.read_log(server, &job)) {
+                Some(logs) => job.logs = logs.into(),
+                None => {
+                    if let Err(e) = job.hydrate_logs(server, limit).await {
+                        self.errors.push(format!("{}: {}", build, e));
+                        continue;
+                    }
+                    if let Some(cache) = cache {
+                        let _ = cache.write_log(server, &job);
+                    }
+                }
+            }
+
+            self.builds += 1;
+            let found = causes.analyze(&job.logs);
+            if found.is_empty() {
+                self.unknown.push(build);
+                continue;
+            }
+            for cause in found {
+                self.causes
+                    .entry((cause.category, cause.name))
+                    .or_default()
+                    .push(build.clone());
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for Analysis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "analyzed {} builds", self.builds)?;
+
+        let mut categories: BTreeMap<&str, usize> = BTreeMap::new();
+        for ((category, _), builds) in &self.causes {
+            *categories.entry(category).or_default() += builds.len();
+        }
+        if !categories.is_empty() {
+            writeln!(f, "\nby category:")?;
+            for (category, count) in categories {
+                let category = if category.is_empty() { "-" } else { category };
+                writeln!(f, "{:>6}  {}", count, category)?;
+            }
+        }
+
+        // most common causes first
+        let mut causes: Vec<_> = self.causes.iter().collect();
+        causes.sort_by_key(|(_, builds)| std::cmp::Reverse(builds.len()));
+        for ((category, name), builds) in causes {
+            writeln!(f, "\n{:>6}  {} ({})", builds.len(), name, category)?;
+            for build in builds {
+                writeln!(f, "        {}", build)?;
+            }
+        }
+        if !self.unknown.is_empty() {
+            writeln!(f, "\n{:>6}  no known cause", self.unknown.len())?;
+            for build in &self.unknown {
+                writeln!(f, "        {}", build)?;
+            }
+        }
+        for error in &self.errors {
+            writeln!(f, "\ncouldn't analyze {}", error)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,526 @@
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+    time::Duration,
+};
+
+use expanduser::expanduser;
+use ratatui::style::{Color, Style};
+use regex::{Regex, RegexSet};
+use serde::Deserialize;
+
+use crate::{
+    cache::Cache,
+    causes::FailureCauses,
+    folding::TimestampMode,
+    jenkins::{read_jenkins_config_file, JenkinsConfig, JenkinsServer},
+    keymap::Keymap,
+    layout::{LayoutPreset, PaneLayout, MIN_PANE_PERCENT},
+    log_store::{LargeLogMode, LogLimit},
+    theme::{self, Element, Preset, Theme},
+};
+
+pub static DEFAULT_JENKINS_CONFIG_PATH: LazyLock<PathBuf> =
+    LazyLock::new(|| expanduser("~/.config/jenkins_jobs/jenkins_jobs.ini").unwrap());
+
+pub static DEFAULT_APP_CONFIG_PATH: LazyLock<PathBuf> =
+    LazyLock::new(|| expanduser("~/.config/jenkins-atom-tui/config.toml").unwrap());
+
+pub static DEFAULT_CAUSES_PATH: LazyLock<PathBuf> =
+    LazyLock::new(|| expanduser("~/.config/jenkins-atom-tui/causes.toml").unwrap());
+
+pub static DEFAULT_KEYMAP_PATH: LazyLock<PathBuf> =
+    LazyLock::new(|| expanduser("~/.config/jenkins-atom-tui/keymap.toml").unwrap());
+
+pub static DEFAULT_SESSION_FILE: LazyLock<PathBuf> =
+    LazyLock::new(|| expanduser("~/.local/state/jenkins-atom-tui/session.json").unwrap());
+
+pub static DEFAULT_CACHE_DIR: LazyLock<PathBuf> =
+    LazyLock::new(|| expanduser("~/.cache/jenkins-atom-tui").unwrap());
+
+/// a scalar value in a server table. TOML and YAML have types, but the JJB INI doesn't, so
+/// everything gets turned back into a string before being turned into a `JenkinsServer`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum ConfigValue {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Bool(bool),
+}
+
+impl Display for ConfigValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigValue::String(s) => f.write_str(s),
+            ConfigValue::Integer(i) => i.fmt(f),
+            ConfigValue::Float(x) => x.fmt(f),
+            ConfigValue::Bool(b) => b.fmt(f),
+        }
+    }
+}
+
+/// how the UI looks
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    /// built-in theme to start from; `no-color` if `NO_COLOR` is set, otherwise `default`
+    pub preset: Option<Preset>,
+    /// theme file with styles to use instead of the preset's
+    pub file: Option<PathBuf>,
+    /// color of the active pane's border and the selected server, instead of the theme's
+    pub accent: Option<Color>,
+    /// only draw with ASCII, for terminals without Unicode
+    pub ascii: bool,
+}
+
+/// how the panes are laid out on startup; runtime changes go to the session file, not here,
+/// and the session's layout overrides this one when it's restored
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LayoutConfig {
+    pub preset: LayoutPreset,
+    /// size of the server list, as a percentage of the screen's width (or height, stacked)
+    pub server_list_percent: u16,
+    /// size of the job list, as a percentage of the screen's height
+    pub job_list_percent: u16,
+    /// hide the server list when there's only one server to pick from
+    pub hide_single_server: bool,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        let layout = PaneLayout::default();
+        Self {
+            preset: layout.preset,
+            server_list_percent: layout.server_list_percent,
+            job_list_percent: layout.job_list_percent,
+            hide_single_server: true,
+        }
+    }
+}
+
+impl LayoutConfig {
+    pub fn pane_layout(&self) -> PaneLayout {
+        PaneLayout {
+            preset: self.preset,
+            server_list_percent: self.server_list_percent,
+            job_list_percent: self.job_list_percent,
+        }
+    }
+}
+
+/// the on-disk cache of jobs and logs
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    pub enabled: bool,
+    pub dir: PathBuf,
+    /// how many MiB of logs to keep before evicting the least recently viewed
+    pub max_size_mb: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dir: DEFAULT_CACHE_DIR.clone(),
+            max_size_mb: 512,
+        }
+    }
+}
+
+/// remembering what was selected between runs
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SessionConfig {
+    /// restore the last session on startup (it's always saved on quit)
+    pub restore: bool,
+    pub file: PathBuf,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            restore: true,
+            file: DEFAULT_SESSION_FILE.clone(),
+        }
+    }
+}
+
+/// how much of which logs to keep around
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogsConfig {
+    /// how many builds' logs to keep in memory; the least recently viewed are dropped first
+    pub max_in_memory: usize,
+    /// logs at least this many MiB are written to a temp file when dropped from memory, rather
+    /// than fetched again next time
+    pub spill_threshold_mb: u64,
+    /// logs over this many MiB are only partially fetched, per `large_log_mode`
+    pub large_log_threshold_mb: u64,
+    pub large_log_mode: LargeLogMode,
+    /// hide `[Pipeline]` bookkeeping lines on startup
+    pub hide_pipeline: bool,
+    /// what to do with Timestamper's timestamps on startup
+    pub timestamps: TimestampMode,
+    /// show line numbers on startup
+    pub line_numbers: bool,
+}
+
+impl Default for LogsConfig {
+    fn default() -> Self {
+        Self {
+            max_in_memory: 8,
+            spill_threshold_mb: 4,
+            large_log_threshold_mb: 64,
+            large_log_mode: LargeLogMode::default(),
+            hide_pipeline: true,
+            timestamps: TimestampMode::default(),
+            line_numbers: false,
+        }
+    }
+}
+
+impl LogsConfig {
+    pub fn spill_threshold(&self) -> u64 {
+        self.spill_threshold_mb * 1024 * 1024
+    }
+
+    /// how much of a log to fetch, unless the user asked for all of it
+    pub fn limit(&self) -> LogLimit {
+        let threshold = self.large_log_threshold_mb * 1024 * 1024;
+        match self.large_log_mode {
+            LargeLogMode::Full => LogLimit::Full,
+            LargeLogMode::Head => LogLimit::Head(threshold),
+            LargeLogMode::Tail => LogLimit::Tail(threshold),
+        }
+    }
+}
+
+/// patterns that a log line is a problem (an error, a failure, ...) if it matches
+pub static DEFAULT_PROBLEM_PATTERNS: [&str; 7] = [
+    r"\bERROR\b",
+    r"\bFAILED\b",
+    r"error\[E\d+\]",
+    r"^Traceback \(most recent call last\)",
+    r"npm ERR!",
+    r"BUILD FAILURE",
+    r"(exit code|exit status|exited with code|returned non-zero exit status) [1-9]",
+];
+
+/// finding problems in logs
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProblemsConfig {
+    /// regexes for lines that are problems; replaces the defaults
+    pub patterns: Vec<String>,
+    /// when a build opens, scroll to its first problem instead of staying put
+    pub jump_to_first: bool,
+}
+
+impl Default for ProblemsConfig {
+    fn default() -> Self {
+        Self {
+            patterns: DEFAULT_PROBLEM_PATTERNS.map(String::from).to_vec(),
+            jump_to_first: false,
+        }
+    }
+}
+
+impl ProblemsConfig {
+    pub fn regex_set(&self) -> Result<RegexSet, regex::Error> {
+        RegexSet::new(&self.patterns)
+    }
+}
+
+/// build-specific noise (timestamps, durations, hashes, temp paths, build numbers) that's
+/// normalized away before diffing two builds' logs
+pub static DEFAULT_DIFF_NORMALIZE: [&str; 6] = [
+    r"\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}(\.\d+)?(Z|[+-]\d{2}:?\d{2})?",
+    r"\b\d{2}:\d{2}:\d{2}(\.\d+)?\b",
+    r"\b\d+(\.\d+)? ?(ms|s|sec|secs|seconds?|min|mins|minutes?)\b",
+    r"\b[0-9a-f]{7,64}\b",
+    r"(/tmp|/var/tmp|/var/folders)/\S+",
+    r"#\d+",
+];
+
+/// comparing two builds' logs
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiffConfig {
+    /// regexes for noise that's replaced before comparing lines; replaces the defaults
+    pub normalize: Vec<String>,
+}
+
+impl Default for DiffConfig {
+    fn default() -> Self {
+        Self {
+            normalize: DEFAULT_DIFF_NORMALIZE.map(String::from).to_vec(),
+        }
+    }
+}
+
+impl DiffConfig {
+    pub fn regexes(&self) -> Result<Vec<Regex>, regex::Error> {
+        self.normalize
+            .iter()
+            .map(|pattern| Regex::new(pattern))
+            .collect()
+    }
+}
+
+/// where saved logs and exported job lists go
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExportConfig {
+    pub dir: PathBuf,
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        Self { dir: ".".into() }
+    }
+}
+
+impl ExportConfig {
+    pub fn dir(&self) -> Result<PathBuf, Box<dyn Error>> {
+        Ok(expanduser(self.dir.to_string_lossy())?)
+    }
+}
+
+/// the app's own config file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppConfig {
+    /// seconds between automatically refreshing the selected server's jobs; 0 to disable
+    pub refresh_interval: u64,
+    /// pane that's active on startup
+    pub default_pane: i8,
+    /// whether logs are wrapped on startup
+    pub wrap_logs: bool,
+    /// capture the mouse on startup; the terminal can't select text while it's captured
+    pub mouse: bool,
+    pub theme: ThemeConfig,
+    pub layout: LayoutConfig,
+    pub cache: CacheConfig,
+    pub session: SessionConfig,
+    pub logs: LogsConfig,
+    pub problems: ProblemsConfig,
+    pub diff: DiffConfig,
+    pub export: ExportConfig,
+    /// command to open URLs with (the URL is passed as its last argument); defaults to
+    /// `xdg-open`, or `open` on macOS
+    pub browser: Option<String>,
+    /// rules file of known failure causes to look for in logs
+    pub failure_causes: PathBuf,
+    /// key bindings file, which overrides the default bindings
+    pub keymap: PathBuf,
+    /// JJB config files to import servers from
+    pub jjb_configs: Vec<PathBuf>,
+    /// map of server name to the same keys a JJB section would have
+    pub servers: BTreeMap<String, BTreeMap<String, ConfigValue>>,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            refresh_interval: 0,
+            default_pane: 1,
+            wrap_logs: false,
+            mouse: true,
+            theme: ThemeConfig::default(),
+            layout: LayoutConfig::default(),
+            cache: CacheConfig::default(),
+            session: SessionConfig::default(),
+            logs: LogsConfig::default(),
+            problems: ProblemsConfig::default(),
+            diff: DiffConfig::default(),
+            export: ExportConfig::default(),
+            browser: None,
+            failure_causes: DEFAULT_CAUSES_PATH.clone(),
+            keymap: DEFAULT_KEYMAP_PATH.clone(),
+            jjb_configs: vec![DEFAULT_JENKINS_CONFIG_PATH.clone()],
+            servers: BTreeMap::new(),
+        }
+    }
+}
+
+impl AppConfig {
+    /// parse the config as TOML, or YAML if the extension says so
+    fn parse(path: &Path, content: &str) -> Result<Self, String> {
+        let config: Self = match path.extension().and_then(|e| e.to_str()) {
+            Some("yaml" | "yml") => serde_yaml::from_str(content).map_err(|e| e.to_string())?,
+            _ => toml::from_str(content).map_err(|e| e.to_string())?,
+        };
+        if !(1..=3).contains(&config.default_pane) {
+            return Err(format!(
+                "default_pane must be 1, 2, or 3, not {}",
+                config.default_pane
+            ));
+        }
+        let percents = [
+            ("server_list_percent", config.layout.server_list_percent),
+            ("job_list_percent", config.layout.job_list_percent),
+        ];
+        for (name, percent) in percents {
+            if !(MIN_PANE_PERCENT..=100 - MIN_PANE_PERCENT).contains(&percent) {
+                return Err(format!(
+                    "layout.{} must be between {} and {}, not {}",
+                    name,
+                    MIN_PANE_PERCENT,
+                    100 - MIN_PANE_PERCENT,
+                    percent
+                ));
+            }
+        }
+        config
+            .problems
+            .regex_set()
+            .map_err(|e| format!("invalid problem pattern: {}", e))?;
+        config
+            .diff
+            .regexes()
+            .map_err(|e| format!("invalid diff normalize pattern: {}", e))?;
+        Ok(config)
+    }
+
+    /// the cache, if it's enabled
+    pub fn cache(&self) -> Option<Cache> {
+        let dir = expanduser(self.cache.dir.to_string_lossy()).ok()?;
+        self.cache
+            .enabled
+            .then(|| Cache::new(dir, self.cache.max_size_mb * 1024 * 1024))
+    }
+
+    /// the known failure causes. not having a rules file at the default path is fine.
+    pub fn failure_causes(&self) -> Result<FailureCauses, Box<dyn Error>> {
+        let path = expanduser(self.failure_causes.to_string_lossy())?;
+        if path == *DEFAULT_CAUSES_PATH && !path.exists() {
+            return Ok(FailureCauses::default());
+        }
+        FailureCauses::load(&path)
+            .map_err(|e| format!("Invalid failure causes {}: {}", path.display(), e).into())
+    }
+
+    /// the key bindings. not having a keymap file at the default path is fine.
+    pub fn keymap(&self) -> Result<Keymap, Box<dyn Error>> {
+        let path = expanduser(self.keymap.to_string_lossy())?;
+        if path == *DEFAULT_KEYMAP_PATH && !path.exists() {
+            return Ok(Keymap::default());
+        }
+        Keymap::load(&path).map_err(|e| format!("Invalid keymap {}: {}", path.display(), e).into())
+    }
+
+    /// the preset theme, with the theme file's styles, if there is one, and then the accent
+    pub fn theme(&self) -> Result<Theme, Box<dyn Error>> {
+        let preset = self.theme.preset.unwrap_or(if theme::no_color() {
+            Preset::NoColor
+        } else {
+            Preset::Default
+        });
+        let mut theme = Theme::new(preset, self.theme.ascii);
+        if let Some(file) = &self.theme.file {
+            let path = expanduser(file.to_string_lossy())?;
+            theme
+                .load(&path)
+                .map_err(|e| format!("Invalid theme {}: {}", path.display(), e))?;
+        }
+        if let Some(accent) = self.theme.accent {
+            theme.set(Element::Accent, Style::new().fg(accent));
+        }
+        Ok(theme)
+    }
+
+    pub fn session_file(&self) -> Option<PathBuf> {
+        expanduser(self.session.file.to_string_lossy()).ok()
+    }
+
+    pub fn refresh_interval(&self) -> Option<Duration> {
+        (self.refresh_interval > 0).then(|| Duration::from_secs(self.refresh_interval))
+    }
+
+    /// build every server from the imported JJB configs and the `servers` table. servers in the
+    /// `servers` table win over imported ones with the same name.
+    pub fn load_servers(&self, section: Option<&str>) -> Result<JenkinsConfig, Box<dyn Error>> {
+        let mut config = JenkinsConfig::default();
+        for jjb_config in &self.jjb_configs {
+            let jjb_config = expanduser(jjb_config.to_string_lossy())?;
+            match read_jenkins_config_file(&jjb_config) {
+                Ok(imported) => {
+                    config.servers.extend(imported.servers);
+                    config.skipped.extend(imported.skipped);
+                }
+                // not having JJB installed is fine if the servers are configured here instead
+                Err(_) if jjb_config == *DEFAULT_JENKINS_CONFIG_PATH && !jjb_config.exists() => {}
+                Err(e) => {
+                    config
+                        .skipped
+                        .insert(jjb_config.display().to_string(), e.to_string());
+                }
+            }
+        }
+
+        for (name, values) in &self.servers {
+            let values: BTreeMap<String, String> = values
+                .iter()
+                .map(|(k, v)| (k.clone(), v.to_string()))
+                .collect();
+            config.skipped.remove(name);
+            match JenkinsServer::try_from(values) {
+                Ok(server) => {
+                    config.servers.insert(name.clone(), server);
+                }
+                Err(reason) => {
+                    config.servers.remove(name);
+                    config.skipped.insert(name.clone(), reason);
+                }
+            }
+        }
+
+        if let Some(section) = section {
+            if !config.servers.contains_key(section) && !config.skipped.contains_key(section) {
+                return Err(format!("No server named [{}]", section).into());
+            }
+            config.servers.retain(|k, _| k == section);
+            config.skipped.retain(|k, _| k == section);
+        }
+        Ok(config)
+    }
+}
+
+/// where configuration comes from, so it can be re-read when refreshing
+#[derive(Debug, Clone, Default)]
+pub struct ConfigSource {
+    /// the app config file
+    pub path: PathBuf,
+    /// if the app config file was explicitly asked for (and so must exist)
+    pub required: bool,
+    /// JJB config to use instead of the app config's `jjb_configs`
+    pub jenkins_config_path: Option<PathBuf>,
+    /// only use this server, like JJB's `--section`
+    pub section: Option<String>,
+}
+
+impl ConfigSource {
+    /// read the app config file, falling back to defaults if it doesn't exist and wasn't
+    /// explicitly asked for
+    pub fn load(&self) -> Result<AppConfig, Box<dyn Error>> {
+        let mut config = match fs::read_to_string(&self.path) {
+            Ok(content) => AppConfig::parse(&self.path, &content)
+                .map_err(|e| format!("Invalid config {}: {}", self.path.display(), e))?,
+            Err(e) if self.required || e.kind() != std::io::ErrorKind::NotFound => {
+                return Err(format!("Cannot read {}: {}", self.path.display(), e).into());
+            }
+            Err(_) => AppConfig::default(),
+        };
+        if let Some(jenkins_config_path) = &self.jenkins_config_path {
+            config.jjb_configs = vec![jenkins_config_path.clone()];
+        }
+        Ok(config)
+    }
+}
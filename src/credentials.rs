@@ -0,0 +1,271 @@
+use std::{collections::BTreeMap, env, fmt::Debug, fs, process::Command, sync::Arc};
+
+use expanduser::expanduser;
+use reqwest::Url;
+use tokio::{sync::OnceCell, task::spawn_blocking};
+use zeroize::Zeroizing;
+
+/// a password/API token that is wiped from memory on drop and never printed by `Debug`
+#[derive(Clone, Default)]
+pub struct Secret(Zeroizing<String>);
+
+impl Secret {
+    /// the actual secret; only call this right before handing it to something that needs it
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Self(Zeroizing::new(value))
+    }
+}
+
+impl Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Secret([redacted])")
+    }
+}
+
+/// where a server's password comes from
+#[derive(Debug, Clone)]
+pub enum Password {
+    Known(Secret),
+    /// `password_command`, run in the background the first time the password is needed. every
+    /// copy of the server shares its output.
+    Command {
+        command: String,
+        output: Arc<OnceCell<Secret>>,
+    },
+}
+
+impl Password {
+    fn command(command: &str) -> Self {
+        Password::Command {
+            command: command.to_string(),
+            output: Arc::default(),
+        }
+    }
+
+    /// the password, if it's known without running anything
+    pub fn get(&self) -> Option<&Secret> {
+        match self {
+            Password::Known(secret) => Some(secret),
+            Password::Command { output, .. } => output.get(),
+        }
+    }
+
+    /// the password, running `password_command` if it hasn't succeeded yet
+    pub async fn resolve(&self) -> Result<&Secret, String> {
+        match self {
+            Password::Known(secret) => Ok(secret),
+            Password::Command { command, output } => {
+                output
+                    .get_or_try_init(|| {
+                        let command = command.clone();
+                        async move {
+                            spawn_blocking(move || run_password_command(&command))
+                                .await
+                                .map_err(|e| format!("Cannot run password_command: {}", e))?
+                        }
+                    })
+                    .await
+            }
+        }
+    }
+}
+
+/// a `machine` (or `default`) entry from a netrc file
+#[derive(Debug, Default)]
+struct NetrcEntry {
+    login: Option<String>,
+    password: Option<Secret>,
+}
+
+/// resolve `${VAR}` to the value of the environment variable; anything else, including a
+/// password that merely starts with `$`, is returned as-is
+fn resolve_env_reference(value: &str) -> Result<String, String> {
+    let Some(name) = value
+        .strip_prefix("${")
+        .and_then(|name| name.strip_suffix('}'))
+    else {
+        return Ok(value.to_string());
+    };
+    env::var(name).map_err(|e| format!("Cannot read password from ${{{}}}: {}", name, e))
+}
+
+/// run `password_command` with the shell and use its trimmed stdout as the password
+fn run_password_command(command: &str) -> Result<Secret, String> {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .output()
+        .map_err(|e| format!("Cannot run password_command: {}", e))?;
+    if !output.status.success() {
+        return Err(format!("password_command exited with {}", output.status));
+    }
+    let stdout = Zeroizing::new(output.stdout);
+    let password = std::str::from_utf8(&stdout)
+        .map_err(|_| "password_command printed non-UTF-8 output")?
+        .trim_end_matches(['\r', '\n'])
+        .to_string();
+    Ok(password.into())
+}
+
+/// look up the entry for `host` in `~/.netrc` (or `$NETRC`), falling back to `default`
+fn read_netrc(host: &str) -> Option<NetrcEntry> {
+    let path = env::var("NETRC")
+        .ok()
+        .or_else(|| Some(expanduser("~/.netrc").ok()?.to_str()?.to_string()))?;
+    let content = Zeroizing::new(fs::read_to_string(path).ok()?);
+    parse_netrc(&content, host)
+}
+
+/// find the entry for `host` in the contents of a netrc file, falling back to `default`
+fn parse_netrc(content: &str, host: &str) -> Option<NetrcEntry> {
+    let lines: Vec<&str> = content.lines().collect();
+    // each token along with the line it's on, so `macdef` bodies can be skipped
+    let mut tokens = lines
+        .iter()
+        .enumerate()
+        .flat_map(|(idx, line)| line.split_whitespace().map(move |token| (idx, token)))
+        .peekable();
+    let mut matched: Option<NetrcEntry> = None;
+    let mut default: Option<NetrcEntry> = None;
+    // which entry the `login`/`password` tokens currently belong to, if we care about it
+    let mut current: Option<&mut NetrcEntry> = None;
+    while let Some((line, token)) = tokens.next() {
+        match token {
+            "machine" => {
+                current = if matched.is_none() && tokens.next().map(|(_, t)| t) == Some(host) {
+                    Some(matched.insert(NetrcEntry::default()))
+                } else {
+                    None
+                };
+            }
+            "default" => current = Some(default.insert(NetrcEntry::default())),
+            "login" => {
+                let login = tokens.next().map(|(_, login)| login.to_string());
+                if let Some(entry) = current.as_mut() {
+                    entry.login = login;
+                }
+            }
+            "password" => {
+                let password = tokens.next().map(|(_, p)| p.to_string().into());
+                if let Some(entry) = current.as_mut() {
+                    entry.password = password;
+                }
+            }
+            // `account` takes a value we don't care about
+            "account" => {
+                tokens.next();
+            }
+            // a macro: its name, then a body running up to the next blank line, which could
+            // hold anything
+            "macdef" => {
+                let line = tokens.next().map_or(line, |(line, _)| line);
+                let body_end = lines[line + 1..]
+                    .iter()
+                    .position(|l| l.trim().is_empty())
+                    .map_or(lines.len(), |end| line + 1 + end);
+                while tokens.next_if(|(line, _)| *line < body_end).is_some() {}
+            }
+            _ => {}
+        }
+    }
+    matched.or(default)
+}
+
+/// figure out the user and password for a JJB section. the password comes from (in order)
+/// `password` (which may be a `${ENV_VAR}` reference), `password_command`, or the netrc entry
+/// for the server's host. the user falls back to the netrc `login`. if there's no user at all
+/// (or it's set to nothing), the server is accessed anonymously.
+pub fn resolve_credentials(
+    section: &BTreeMap<String, String>,
+    url: &str,
+) -> Result<Option<(String, Password)>, String> {
+    // an empty value lets a section opt out of credentials inherited from `[jenkins]`
+    let get = |key| section.get(key).filter(|v| !v.is_empty());
+    let mut user = get("user").cloned();
+    let mut password = match (get("password"), get("password_command")) {
+        (Some(password), _) => Some(Password::Known(resolve_env_reference(password)?.into())),
+        // it's only run when the password is first needed, so reading the config never waits
+        (None, Some(command)) => Some(Password::command(command)),
+        (None, None) => None,
+    };
+
+    if user.is_none() || password.is_none() {
+        let host = Url::parse(url)
+            .map_err(|e| format!("Invalid URL: {}", e))?
+            .host_str()
+            .unwrap_or_default()
+            .to_string();
+        if let Some(entry) = read_netrc(&host) {
+            user = user.or(entry.login);
+            password = password.or(entry.password.map(Password::Known));
+        }
+    }
+
+    match (user, password) {
+        (Some(user), Some(password)) => Ok(Some((user, password))),
+        (Some(user), None) => Err(format!("Missing password for user {}", user)),
+        (None, _) => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn netrc_matches_host() {
+        let netrc = "machine other login nobody password nope\n\
+                     machine ci.example.com\n  login me\n  password s3cret\n";
+        let entry = parse_netrc(netrc, "ci.example.com").unwrap();
+        assert_eq!(entry.login.as_deref(), Some("me"));
+        assert_eq!(entry.password.unwrap().expose(), "s3cret");
+    }
+
+    #[test]
+    fn netrc_falls_back_to_default() {
+        let netrc = "machine other login nobody password nope\ndefault login anon password guest\n";
+        let entry = parse_netrc(netrc, "ci.example.com").unwrap();
+        assert_eq!(entry.login.as_deref(), Some("anon"));
+        assert_eq!(entry.password.unwrap().expose(), "guest");
+        assert!(parse_netrc("machine other login nobody\n", "ci.example.com").is_none());
+    }
+
+    #[test]
+    fn netrc_skips_macdef_bodies() {
+        let netrc = "macdef init\n\
+                     machine ci.example.com login mallory password stolen\n\
+                     \n\
+                     machine ci.example.com login me password s3cret\n";
+        let entry = parse_netrc(netrc, "ci.example.com").unwrap();
+        assert_eq!(entry.login.as_deref(), Some("me"));
+        assert_eq!(entry.password.unwrap().expose(), "s3cret");
+    }
+
+    #[test]
+    fn netrc_macdef_without_end_runs_to_eof() {
+        let netrc = "macdef init\nmachine ci.example.com login mallory password stolen\n";
+        assert!(parse_netrc(netrc, "ci.example.com").is_none());
+    }
+
+    #[test]
+    fn env_reference_needs_braces() {
+        assert_eq!(resolve_env_reference("$ecret").unwrap(), "$ecret");
+        assert_eq!(resolve_env_reference("pa$$word").unwrap(), "pa$$word");
+    }
+
+    #[test]
+    fn unset_env_reference_is_an_error() {
+        let err = resolve_env_reference("${JENKINS_ATOM_TUI_TEST_UNSET_VARIABLE}").unwrap_err();
+        assert!(
+            err.contains("JENKINS_ATOM_TUI_TEST_UNSET_VARIABLE"),
+            "{}",
+            err
+        );
+    }
+}
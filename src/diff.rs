@@ -0,0 +1,318 @@
+use std::{
+    borrow::Cow,
+    time::{Duration, Instant},
+};
+
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::Style,
+    widgets::{Scrollbar, ScrollbarOrientation, ScrollbarState, StatefulWidget},
+};
+use regex::Regex;
+use similar::{capture_diff_slices_deadline, Algorithm, DiffTag};
+
+use crate::{
+    log_view::{render_row, Log, LogScrollState},
+    theme::{Element, Theme},
+};
+
+/// how long to spend looking for the smallest diff before settling for a bigger one
+const DIFF_DEADLINE: Duration = Duration::from_secs(2);
+
+/// how a row of the diff differs between the two logs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RowKind {
+    Same,
+    Changed,
+    /// only in the left log
+    Removed,
+    /// only in the right log
+    Added,
+}
+
+/// a row of the diff: a line from either log, or both, side by side
+#[derive(Debug, Clone)]
+pub struct DiffRow {
+    pub left: Option<usize>,
+    pub right: Option<usize>,
+    pub kind: RowKind,
+}
+
+/// a line diff between two builds' logs, after normalizing away the noise that differs
+/// between every build
+#[derive(Debug, Clone, Default)]
+pub struct LogDiff {
+    pub rows: Vec<DiffRow>,
+    /// rows where a run of changes starts
+    hunks: Vec<usize>,
+    pub added: usize,
+    pub removed: usize,
+    pub changed: usize,
+}
+
+/// replace everything matching any of `normalize` so it compares equal
+fn normalize<'a>(line: &'a str, normalize: &[Regex]) -> Cow<'a, str> {
+    normalize.iter().fold(Cow::Borrowed(line), |line, regex| {
+        match regex.replace_all(&line, "*") {
+            Cow::Borrowed(_) => line,
+            Cow::Owned(replaced) => Cow::Owned(replaced),
+        }
+    })
+}
+
+impl LogDiff {
+    pub fn new(left: &Log, right: &Log, normalizers: &[Regex]) -> Self {
+        let lines = |log: &Log| -> Vec<String> {
+            (0..log.line_count())
+                .map(|idx| normalize(log.line(idx), normalizers).into_owned())
+                .collect()
+        };
+        let (old, new) = (lines(left), lines(right));
+        let ops = capture_diff_slices_deadline(
+            Algorithm::Myers,
+            &old,
+            &new,
+            Some(Instant::now() + DIFF_DEADLINE),
+        );
+
+        let mut diff = Self::default();
+        for op in ops {
+            let (tag, old_range, new_range) = op.as_tag_tuple();
+            if tag != DiffTag::Equal {
+                diff.hunks.push(diff.rows.len());
+            }
+            match tag {
+                DiffTag::Equal => {
+                    diff.rows
+                        .extend(old_range.zip(new_range).map(|(l, r)| DiffRow {
+                            left: Some(l),
+                            right: Some(r),
+                            kind: RowKind::Same,
+                        }))
+                }
+                // pair up replaced lines, and whatever's left over was removed or added
+                _ => {
+                    let rows = old_range.len().max(new_range.len());
+                    for offset in 0..rows {
+                        let left = (offset < old_range.len()).then(|| old_range.start + offset);
+                        let right = (offset < new_range.len()).then(|| new_range.start + offset);
+                        let kind = match (left, right) {
+                            (Some(_), Some(_)) => RowKind::Changed,
+                            (Some(_), None) => RowKind::Removed,
+                            _ => RowKind::Added,
+                        };
+                        match kind {
+                            RowKind::Changed => diff.changed += 1,
+                            RowKind::Removed => diff.removed += 1,
+                            _ => diff.added += 1,
+                        }
+                        diff.rows.push(DiffRow { left, right, kind });
+                    }
+                }
+            }
+        }
+        diff
+    }
+
+    /// the first row of the next run of changes after `row`
+    pub fn next_change(&self, row: usize) -> Option<usize> {
+        let idx = self.hunks.partition_point(|hunk| *hunk <= row);
+        self.hunks.get(idx).copied()
+    }
+
+    /// the first row of the previous run of changes before `row`
+    pub fn previous_change(&self, row: usize) -> Option<usize> {
+        let idx = self.hunks.partition_point(|hunk| *hunk < row);
+        idx.checked_sub(1).map(|idx| self.hunks[idx])
+    }
+}
+
+/// renders two logs side by side, lined up by a `LogDiff`. both sides scroll together.
+pub struct DiffView<'a> {
+    left: &'a Log,
+    right: &'a Log,
+    diff: &'a LogDiff,
+    theme: &'a Theme,
+}
+
+impl<'a> DiffView<'a> {
+    pub fn new(left: &'a Log, right: &'a Log, diff: &'a LogDiff, theme: &'a Theme) -> Self {
+        Self {
+            left,
+            right,
+            diff,
+            theme,
+        }
+    }
+
+    /// marker and style for a row on one side of the diff
+    fn row_style(&self, kind: RowKind) -> (&'static str, Style) {
+        match kind {
+            RowKind::Same => ("  ", Style::default()),
+            RowKind::Changed => ("~ ", self.theme.style(Element::Changed)),
+            RowKind::Removed => ("- ", self.theme.style(Element::Removed)),
+            RowKind::Added => ("+ ", self.theme.style(Element::Added)),
+        }
+    }
+}
+
+impl StatefulWidget for DiffView<'_> {
+    type State = LogScrollState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        // leave room for the scrollbars, and a separator between the two sides
+        let text_area = Rect {
+            width: area.width.saturating_sub(1),
+            height: area.height.saturating_sub(1),
+            ..area
+        };
+        let [left_area, separator, right_area] = Layout::horizontal([
+            Constraint::Fill(1),
+            Constraint::Length(1),
+            Constraint::Fill(1),
+        ])
+        .areas(text_area);
+        // each side has a 2 column gutter for its markers
+        let gutter = 2;
+        let text_width = left_area.width.saturating_sub(gutter);
+        state.set_content(
+            Rect {
+                width: text_width,
+                ..text_area
+            },
+            self.diff.rows.len(),
+            self.left.max_width().max(self.right.max_width()),
+        );
+
+        let (x, y) = state.offset();
+        for screen_y in separator.top()..separator.bottom() {
+            buf.set_string(
+                separator.x,
+                screen_y,
+                self.theme.symbols.separator,
+                Style::default(),
+            );
+        }
+        for (row, screen_y) in self.diff.rows[y.min(self.diff.rows.len())..]
+            .iter()
+            .zip(text_area.top()..text_area.bottom())
+        {
+            let (marker, style) = self.row_style(row.kind);
+            for (side_area, log, line) in [
+                (left_area, self.left, row.left),
+                (right_area, self.right, row.right),
+            ] {
+                let Some(line) = line else {
+                    continue;
+                };
+                buf.set_stringn(
+                    side_area.x,
+                    screen_y,
+                    marker,
+                    side_area.width as usize,
+                    style,
+                );
+                let text_area = Rect {
+                    x: side_area.x + gutter.min(side_area.width),
+                    width: side_area.width.saturating_sub(gutter),
+                    ..side_area
+                };
+                render_row(buf, text_area, screen_y, log.line(line), x, style);
+            }
+        }
+
+        if self.diff.rows.len() > text_area.height as usize {
+            let mut vertical =
+                ScrollbarState::new(self.diff.rows.len() - text_area.height as usize).position(y);
+            Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                .symbols(self.theme.symbols.vertical_scrollbar.clone())
+                .render(area, buf, &mut vertical);
+        }
+        let content_width = self.left.max_width().max(self.right.max_width());
+        if content_width > text_width as u32 {
+            let mut horizontal =
+                ScrollbarState::new((content_width - text_width as u32) as usize).position(x);
+            Scrollbar::new(ScrollbarOrientation::HorizontalBottom)
+                .symbols(self.theme.symbols.horizontal_scrollbar.clone())
+                .render(
+                    Rect {
+                        width: text_area.width,
+                        ..area
+                    },
+                    buf,
+                    &mut horizontal,
+                );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(text: &str) -> Log {
+        Log::from(text.to_string())
+    }
+
+    fn kinds(diff: &LogDiff) -> Vec<RowKind> {
+        diff.rows.iter().map(|row| row.kind).collect()
+    }
+
+    #[test]
+    fn identical_logs_have_no_changes() {
+        let text = "a\nb\nc";
+        let diff = LogDiff::new(&log(text), &log(text), &[]);
+        assert_eq!(kinds(&diff), [RowKind::Same; 3]);
+        assert_eq!((diff.added, diff.removed, diff.changed), (0, 0, 0));
+        assert_eq!(diff.next_change(0), None);
+    }
+
+    #[test]
+    fn empty_logs() {
+        let diff = LogDiff::new(&Log::default(), &Log::default(), &[]);
+        assert!(diff.rows.is_empty());
+        let diff = LogDiff::new(&Log::default(), &log("a\nb"), &[]);
+        assert_eq!(kinds(&diff), [RowKind::Added; 2]);
+        assert_eq!(diff.added, 2);
+        let diff = LogDiff::new(&log("a\nb"), &Log::default(), &[]);
+        assert_eq!(kinds(&diff), [RowKind::Removed; 2]);
+        assert_eq!(diff.removed, 2);
+    }
+
+    #[test]
+    fn pairs_up_changed_lines() {
+        let diff = LogDiff::new(&log("a\nb\nc\nd"), &log("a\nB\nc\nd\ne"), &[]);
+        assert_eq!(
+            kinds(&diff),
+            [
+                RowKind::Same,
+                RowKind::Changed,
+                RowKind::Same,
+                RowKind::Same,
+                RowKind::Added
+            ]
+        );
+        let changed = &diff.rows[1];
+        assert_eq!((changed.left, changed.right), (Some(1), Some(1)));
+        assert_eq!((diff.added, diff.removed, diff.changed), (1, 0, 1));
+        assert_eq!(diff.next_change(0), Some(1));
+        assert_eq!(diff.next_change(1), Some(4));
+    }
+
+    #[test]
+    fn normalizers_hide_noise() {
+        let left = log("build 41 took 12s\nok");
+        let right = log("build 42 took 9s\nok");
+        let diff = LogDiff::new(&left, &right, &[]);
+        assert_eq!(diff.changed, 1);
+        let normalizers = [Regex::new(r"\d+").unwrap()];
+        let diff = LogDiff::new(&left, &right, &normalizers);
+        assert_eq!(kinds(&diff), [RowKind::Same; 2]);
+        assert_eq!(normalize("took 12s", &normalizers), "took *s");
+        assert!(matches!(
+            normalize("no digits", &normalizers),
+            Cow::Borrowed(_)
+        ));
+    }
+}
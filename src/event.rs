@@ -1,66 +1,118 @@
 use std::{error::Error, time::Duration};
 
-use crossterm::event::{EventStream, KeyEvent, KeyEventKind};
+use crossterm::event::{EventStream, KeyEvent, KeyEventKind, MouseEvent, MouseEventKind};
 use futures::{FutureExt, StreamExt};
 use tokio::{select, sync::mpsc};
 
+use crate::app::{BuildDiff, BuildTrigger, FetchedJobs, FetchedLogs};
+
 #[derive(Debug)]
 pub enum Event {
     Tick,
     Key(KeyEvent),
+    Mouse(MouseEvent),
     Resize(u16, u16),
     RefreshJobsForServer,
     RefreshLogsForJob,
+    /// like `RefreshLogsForJob`, but fetch the whole log no matter how big it is
+    LoadFullLogsForJob,
+    /// suspend the TUI to show the selected build's logs in `$PAGER`
+    PageLogs,
+    /// suspend the TUI to open the selected build's logs in `$EDITOR`
+    EditLogs,
+    /// turn mouse capture off so the terminal can select text, or back on
+    ToggleMouseCapture,
+    /// refresh every server's jobs, not just the selected one's
+    RefreshAllJobs,
+    /// start a build the user said yes to
+    TriggerBuild(BuildTrigger),
+    /// a build was started in the background, or couldn't be
+    BuildTriggered(BuildTrigger, Result<(), String>),
+    /// a server's jobs came back from being fetched in the background
+    JobsFetched(Box<FetchedJobs>),
+    /// a build's logs came back from being fetched in the background
+    LogsFetched(Box<FetchedLogs>),
+    /// diff the marked build's logs against the selected build's in the background
+    DiffLogs,
+    /// a diff came back from being worked out in the background
+    DiffComputed(Box<BuildDiff>),
 }
 
-#[allow(dead_code)] // TODO: figure out where this could be used if necessary
+/// sends events to the app, from wherever they happen
+pub type EventSender = mpsc::UnboundedSender<Event>;
+
 #[derive(Debug)]
 pub struct EventHandler {
-    sender: mpsc::UnboundedSender<Event>,
+    sender: EventSender,
     receiver: mpsc::UnboundedReceiver<Event>,
     handler: tokio::task::JoinHandle<()>,
+    tick_rate: Duration,
+}
+
+/// forward terminal events and ticks to `sender` until it's closed
+fn spawn_reader(
+    sender: mpsc::UnboundedSender<Event>,
+    tick_rate: Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut reader = EventStream::new();
+        let mut tick = tokio::time::interval(tick_rate);
+        loop {
+            let tick_delay = tick.tick();
+            let event = reader.next().fuse();
+            select! {
+                _ = sender.closed() => { break; },
+                _ = tick_delay => { sender.send(Event::Tick).unwrap() },
+                Some(Ok(evt)) = event => {
+                    match evt {
+                        crossterm::event::Event::FocusGained => {},
+                        crossterm::event::Event::FocusLost => {},
+                        crossterm::event::Event::Key(key_event) => {
+                            if key_event.kind == KeyEventKind::Press {
+                                sender.send(Event::Key(key_event)).unwrap()
+                            }
+                        },
+                        // the pointer moving with no buttons down is never interesting
+                        crossterm::event::Event::Mouse(mouse_event) => {
+                            if mouse_event.kind != MouseEventKind::Moved {
+                                sender.send(Event::Mouse(mouse_event)).unwrap()
+                            }
+                        },
+                        crossterm::event::Event::Paste(_) => {},
+                        crossterm::event::Event::Resize(x, y) => {sender.send(Event::Resize(x, y)).unwrap()},
+                    }
+                }
+            }
+        }
+    })
 }
 
 impl EventHandler {
     pub fn new(tick_rate: u64) -> Self {
         let tick_rate = Duration::from_millis(tick_rate);
         let (sender, receiver) = mpsc::unbounded_channel();
-        let _sender = sender.clone();
-
-        let handler = tokio::spawn(async move {
-            let mut reader = EventStream::new();
-            let mut tick = tokio::time::interval(tick_rate);
-            loop {
-                let tick_delay = tick.tick();
-                let event = reader.next().fuse();
-                select! {
-                    _ = _sender.closed() => { break; },
-                    _ = tick_delay => { _sender.send(Event::Tick).unwrap() },
-                    Some(Ok(evt)) = event => {
-                        match evt {
-                            crossterm::event::Event::FocusGained => {},
-                            crossterm::event::Event::FocusLost => {},
-                            crossterm::event::Event::Key(key_event) => {
-                                if key_event.kind == KeyEventKind::Press {
-                                    _sender.send(Event::Key(key_event)).unwrap()
-                                }
-                            },
-                            crossterm::event::Event::Mouse(_) => {},
-                            crossterm::event::Event::Paste(_) => {},
-                            crossterm::event::Event::Resize(x, y) => {_sender.send(Event::Resize(x, y)).unwrap()},
-                        }
-                    }
-                }
-            }
-        });
+        let handler = spawn_reader(sender.clone(), tick_rate);
 
         Self {
             sender,
             receiver,
             handler,
+            tick_rate,
         }
     }
 
+    /// stop reading from the terminal, so another program can have it
+    pub async fn pause(&mut self) {
+        self.handler.abort();
+        // wait for the reader to actually be dropped, so it isn't still reading keypresses
+        let _ = (&mut self.handler).await;
+    }
+
+    /// start reading from the terminal again after `pause`
+    pub fn resume(&mut self) {
+        self.handler = spawn_reader(self.sender.clone(), self.tick_rate);
+    }
+
     pub async fn next(&mut self) -> Result<Event, Box<dyn Error>> {
         self.receiver.recv().await.ok_or("IO Error?".into())
     }
@@ -69,4 +121,9 @@ impl EventHandler {
     pub fn push_event(&self, event: Event) {
         self.sender.send(event).unwrap();
     }
+
+    /// somewhere background tasks can send events to once they're done
+    pub fn sender(&self) -> EventSender {
+        self.sender.clone()
+    }
 }
@@ -0,0 +1,237 @@
+use std::{
+    borrow::Cow,
+    env, fs,
+    io::{self, Write},
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus, Stdio},
+    sync::LazyLock,
+};
+
+use base64::prelude::{Engine, BASE64_STANDARD};
+use regex::Regex;
+use tempfile::NamedTempFile;
+
+use crate::jenkins::JenkinsResult;
+
+/// Jenkins hides serialized "console notes" in logs between `ESC[8m` and `ESC[0m`
+static CONSOLE_NOTE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\x1b\[8mha:[^\x1b]*\x1b\[0m").unwrap());
+
+/// CSI sequences (colors and such) and OSC sequences (titles, hyperlinks)
+static ANSI_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\x1b\[[0-?]*[ -/]*[@-~]|\x1b\][^\x07\x1b]*(?:\x07|\x1b\\)").unwrap()
+});
+
+/// remove Jenkins' console notes, which are never meant to be seen, but keep colors
+pub fn strip_console_notes(text: &str) -> Cow<'_, str> {
+    CONSOLE_NOTE_REGEX.replace_all(text, "")
+}
+
+/// remove console notes and every escape sequence, leaving plain text
+pub fn strip_ansi(text: &str) -> String {
+    ANSI_REGEX
+        .replace_all(&strip_console_notes(text), "")
+        .into_owned()
+}
+
+/// `name` without anything a file system would choke on
+pub fn sanitize(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// a file name for something about `job`
+pub fn file_name(job: &JenkinsResult, extension: &str) -> String {
+    format!("{}-{}.{}", sanitize(&job.name), job.build_number, extension)
+}
+
+/// a file name for some of `job`'s log lines
+pub fn lines_file_name(
+    job: &JenkinsResult,
+    lines: &RangeInclusive<usize>,
+    extension: &str,
+) -> String {
+    format!(
+        "{}-{}-lines-{}-{}.{}",
+        sanitize(&job.name),
+        job.build_number,
+        lines.start() + 1,
+        lines.end() + 1,
+        extension
+    )
+}
+
+/// write `contents` to `name` in `dir`, returning where it went
+pub fn save(dir: &Path, name: &str, contents: &str) -> io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(name);
+    fs::write(&path, contents)?;
+    Ok(path)
+}
+
+/// write `contents` to a new temp file that only we can read, named after `job`'s build; it's
+/// deleted when it's dropped
+pub fn save_temp(
+    job: &JenkinsResult,
+    extension: &str,
+    contents: &str,
+) -> io::Result<NamedTempFile> {
+    let mut file = tempfile::Builder::new()
+        .prefix(&format!("{}-{}-", sanitize(&job.name), job.build_number))
+        .suffix(&format!(".{}", extension))
+        .tempfile()?;
+    file.write_all(contents.as_bytes())?;
+    file.flush()?;
+    Ok(file)
+}
+
+fn csv_field(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+/// one row per build: name, number, state, when it was updated, known causes, and link
+fn job_rows(jobs: &[JenkinsResult]) -> impl Iterator<Item = [String; 6]> + '_ {
+    jobs.iter().map(|job| {
+        let causes: Vec<&str> = job.causes.iter().map(|c| c.name.as_str()).collect();
+        [
+            job.name.clone(),
+            job.build_number.to_string(),
+            format!("{:?}", job.build_state),
+            job.updated.to_rfc3339(),
+            causes.join("; "),
+            job.link.to_string(),
+        ]
+    })
+}
+
+const JOB_COLUMNS: [&str; 6] = ["job", "build", "state", "updated", "causes", "link"];
+
+pub fn jobs_csv(jobs: &[JenkinsResult]) -> String {
+    let mut csv = format!("{}\n", JOB_COLUMNS.join(","));
+    for row in job_rows(jobs) {
+        let fields: Vec<Cow<str>> = row.iter().map(|field| csv_field(field)).collect();
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+pub fn jobs_markdown(jobs: &[JenkinsResult]) -> String {
+    let mut markdown = format!(
+        "| {} |\n|{}\n",
+        JOB_COLUMNS.join(" | "),
+        " --- |".repeat(JOB_COLUMNS.len())
+    );
+    for row in job_rows(jobs) {
+        let fields: Vec<String> = row.iter().map(|field| field.replace('|', "\\|")).collect();
+        markdown.push_str(&format!("| {} |\n", fields.join(" | ")));
+    }
+    markdown
+}
+
+/// the most that goes on the clipboard. terminals cap how long an OSC 52 sequence can be (often
+/// to 100KB or less), dropping or choking on longer ones.
+const MAX_CLIPBOARD_BYTES: usize = 100_000;
+
+/// put `text` on the system clipboard with an OSC 52 escape sequence, which the terminal
+/// handles, so it works over SSH too. only the first `MAX_CLIPBOARD_BYTES` of it go, cut at a
+/// line break if there is one; returns how many bytes that was.
+pub fn copy_to_clipboard(text: &str) -> io::Result<usize> {
+    let mut end = text.len().min(MAX_CLIPBOARD_BYTES);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    if end < text.len() {
+        end = text[..end].rfind('\n').unwrap_or(end);
+    }
+    let mut stdout = io::stdout();
+    write!(
+        stdout,
+        "\x1b]52;c;{}\x07",
+        BASE64_STANDARD.encode(&text[..end])
+    )?;
+    stdout.flush()?;
+    Ok(end)
+}
+
+/// open `url` with `command` (or the platform's usual opener), without waiting for it or
+/// letting it write over the TUI
+pub fn open_url(command: Option<&str>, url: &str) -> io::Result<()> {
+    let command = command.unwrap_or(if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    });
+    Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", command))
+        .arg("sh")
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map(|mut child| {
+            // reap it once it's done
+            std::thread::spawn(move || child.wait());
+        })
+}
+
+/// the user's pager, or `less`
+fn pager() -> String {
+    env::var("PAGER").unwrap_or_else(|_| "less".to_string())
+}
+
+/// the user's editor, or `vi`
+fn editor() -> String {
+    env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string())
+}
+
+/// pipe `text` into the pager. it runs through `sh` since `$PAGER` may have arguments.
+pub fn page(text: &str) -> io::Result<ExitStatus> {
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(pager()).stdin(Stdio::piped());
+    // let less show colors unless the user configured it otherwise
+    if env::var_os("LESS").is_none() {
+        command.env("LESS", "R");
+    }
+    let mut child = command.spawn()?;
+    let written = match child.stdin.take() {
+        // stdin is closed once it's written, so the pager sees the end of the text
+        Some(mut stdin) => stdin.write_all(text.as_bytes()),
+        None => Ok(()),
+    };
+    // the pager's always waited for, so it isn't left behind holding the terminal
+    let status = child.wait()?;
+    match written {
+        // quitting the pager before reading everything is fine
+        Err(e) if e.kind() != io::ErrorKind::BrokenPipe => Err(e),
+        _ => Ok(status),
+    }
+}
+
+/// open `path` in the editor at `line` (starting at 1). `+line` works for vi, nano, emacs,
+/// and most others.
+pub fn edit(path: &Path, line: usize) -> io::Result<ExitStatus> {
+    Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$@\"", editor()))
+        .arg("sh")
+        .arg(format!("+{}", line))
+        .arg(path)
+        .status()
+}
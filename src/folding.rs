@@ -0,0 +1,542 @@
+use std::{borrow::Cow, collections::HashSet, ops::Range, sync::LazyLock};
+
+use chrono::{DateTime, NaiveTime};
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::{
+    log_view::{display_width, Log, WrapRows},
+    theme,
+};
+
+/// Timestamper's prefixes: `[2024-05-01T10:00:00.123Z] ` in pipelines, `10:00:00  ` in
+/// freestyle jobs
+static TIMESTAMP_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:\[(?P<iso>\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}(?:\.\d+)?Z)\] |(?P<time>\d{2}:\d{2}:\d{2}(?:\.\d+)?)  )")
+        .unwrap()
+});
+
+/// `[Pipeline] { (Build)` opens a block labeled "Build"; a bare `[Pipeline] {` is labeled by
+/// the step before it
+static BLOCK_START_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\[Pipeline\] \{(?: \((?P<label>.*)\))?$").unwrap());
+
+const PIPELINE_PREFIX: &str = "[Pipeline] ";
+const BLOCK_END: &str = "[Pipeline] }";
+
+/// what to do with Timestamper's timestamps
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimestampMode {
+    /// leave them as they are
+    #[default]
+    Show,
+    Strip,
+    /// time since the first timestamp in the log
+    Relative,
+}
+
+impl TimestampMode {
+    pub fn next(self) -> Self {
+        match self {
+            TimestampMode::Show => TimestampMode::Strip,
+            TimestampMode::Strip => TimestampMode::Relative,
+            TimestampMode::Relative => TimestampMode::Show,
+        }
+    }
+}
+
+/// a `[Pipeline] {` ... `[Pipeline] }` block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fold {
+    /// line of the `[Pipeline] {`
+    pub start: usize,
+    /// line of the matching `[Pipeline] }`, or the last line if it hasn't been closed yet
+    pub end: usize,
+    pub label: String,
+}
+
+/// length of the line's Timestamper prefix, or 0 if it hasn't got one
+fn timestamp_len(line: &str) -> usize {
+    TIMESTAMP_REGEX.find(line).map_or(0, |found| found.end())
+}
+
+/// the time in a Timestamper prefix, in milliseconds (since the epoch, or since midnight for
+/// time-only prefixes)
+fn parse_timestamp(prefix: &str) -> Option<i64> {
+    let captures = TIMESTAMP_REGEX.captures(prefix)?;
+    if let Some(iso) = captures.name("iso") {
+        DateTime::parse_from_rfc3339(iso.as_str())
+            .ok()
+            .map(|t| t.timestamp_millis())
+    } else {
+        captures
+            .name("time")
+            .and_then(|time| NaiveTime::parse_from_str(time.as_str(), "%H:%M:%S%.f").ok())
+            .map(|t| (t - NaiveTime::MIN).num_milliseconds())
+    }
+}
+
+/// what parsing found out about a line, so showing it doesn't need to parse it again
+#[derive(Debug, Clone, Copy)]
+struct LineInfo {
+    /// length of its Timestamper prefix, if it has one
+    timestamp_len: u8,
+    /// it's `[Pipeline]` bookkeeping
+    pipeline: bool,
+}
+
+/// where parsing stood before a line, to pick up from there again
+#[derive(Debug, Clone)]
+struct Checkpoint {
+    folds: usize,
+    open: Vec<usize>,
+    last_step: Option<usize>,
+}
+
+/// a log's pipeline blocks, parsed as more of the log comes in
+#[derive(Debug, Clone, Default)]
+pub struct Folds {
+    /// every block found so far, outermost first
+    folds: Vec<Fold>,
+    /// indexes into `folds` of the blocks that are still open
+    open: Vec<usize>,
+    /// line of the last pipeline step, which labels a bare `[Pipeline] {`
+    last_step: Option<usize>,
+    /// each line parsed so far
+    lines: Vec<LineInfo>,
+    /// how things stood before the last line parsed, which might not have been finished
+    before_last: Option<Checkpoint>,
+}
+
+impl Folds {
+    /// parse the lines of `log` that haven't been parsed yet, and the last one again in case
+    /// more of it has come in since
+    pub fn parse(&mut self, log: &Log) {
+        if let Some(checkpoint) = self.before_last.take() {
+            self.folds.truncate(checkpoint.folds);
+            self.open = checkpoint.open;
+            self.last_step = checkpoint.last_step;
+            self.lines.pop();
+        }
+        // it's not the log that was parsed before
+        if self.lines.len() > log.line_count() {
+            *self = Self::default();
+        }
+        let last = log.line_count().saturating_sub(1);
+        for idx in self.lines.len()..log.line_count() {
+            if idx == last {
+                self.before_last = Some(Checkpoint {
+                    folds: self.folds.len(),
+                    open: self.open.clone(),
+                    last_step: self.last_step,
+                });
+            }
+            let raw = log.line(idx);
+            let timestamp_len = timestamp_len(raw);
+            let line = &raw[timestamp_len..];
+            if let Some(captures) = BLOCK_START_REGEX.captures(line) {
+                let label = captures.name("label").map_or_else(
+                    || self.last_step.map_or("", |step| step_of(log, step)),
+                    |label| label.as_str(),
+                );
+                self.open.push(self.folds.len());
+                self.folds.push(Fold {
+                    start: idx,
+                    end: last,
+                    label: label.to_string(),
+                });
+            } else if line == BLOCK_END {
+                if let Some(fold) = self.open.pop() {
+                    self.folds[fold].end = idx;
+                }
+            } else if line.starts_with(PIPELINE_PREFIX) {
+                self.last_step = Some(idx);
+            }
+            self.lines.push(LineInfo {
+                timestamp_len: timestamp_len.try_into().unwrap_or_default(),
+                pipeline: line.starts_with(PIPELINE_PREFIX),
+            });
+        }
+        // the blocks that are still open run to the end, for now
+        for open in &self.open {
+            self.folds[*open].end = last;
+        }
+    }
+
+    /// every block, outermost first
+    pub fn folds(&self) -> &[Fold] {
+        &self.folds
+    }
+}
+
+/// the step on `line` of `log`, without its `[Pipeline] ` prefix
+fn step_of(log: &Log, line: usize) -> &str {
+    let raw = log.line(line);
+    raw[timestamp_len(raw)..]
+        .strip_prefix(PIPELINE_PREFIX)
+        .unwrap_or_default()
+}
+
+/// how to show a log: which folds are collapsed, and what's hidden or reformatted
+#[derive(Debug, Clone, Default)]
+pub struct FoldOptions {
+    /// `start` lines of the collapsed folds
+    pub collapsed: HashSet<usize>,
+    /// hide `[Pipeline]` bookkeeping lines
+    pub hide_pipeline: bool,
+    pub timestamps: TimestampMode,
+    /// mark folds with ASCII rather than triangles
+    pub ascii: bool,
+}
+
+/// one line of a folded log, and where its text comes from
+#[derive(Debug, Clone)]
+struct ShownLine {
+    /// line of the original log
+    original: usize,
+    /// text to show before (what's shown of) the original line, as a range of `extra`
+    prefix: Range<u32>,
+    /// how many bytes at the start of the original line to leave out, if any of it is shown
+    skip: Option<u8>,
+}
+
+/// a log as it's shown, with folds collapsed and noise hidden. it refers to the lines of the log
+/// it came from rather than copying them, so only what's added (fold labels and relative
+/// timestamps) takes up any more room.
+#[derive(Debug, Default)]
+pub struct FoldedLog {
+    lines: Vec<ShownLine>,
+    /// text that isn't in the original log
+    extra: String,
+    /// display width of each shown line
+    line_widths: Vec<u32>,
+    max_width: u32,
+    /// shown lines with problems on them (or, for collapsed folds, in them)
+    problems: Vec<usize>,
+    wrap_rows: WrapRows,
+}
+
+impl FoldedLog {
+    /// how `source` is shown, or `None` if it's shown as it is
+    pub fn new(source: &Log, folds: &Folds, options: &FoldOptions) -> Option<Self> {
+        let hides_pipeline = options.hide_pipeline && folds.lines.iter().any(|line| line.pipeline);
+        if folds.folds.is_empty() && options.timestamps == TimestampMode::Show && !hides_pipeline {
+            return None;
+        }
+
+        let fold_starts: Vec<usize> = folds.folds.iter().map(|fold| fold.start).collect();
+        let symbols = theme::symbols(options.ascii);
+        let mut folded = Self::default();
+        let mut first_timestamp = None;
+
+        let mut idx = 0;
+        while idx < source.line_count().min(folds.lines.len()) {
+            let info = folds.lines[idx];
+            let timestamp_len = info.timestamp_len as usize;
+            let raw = source.line(idx);
+            let stamp = &raw[..timestamp_len];
+            let prefix_start = folded.extra.len();
+            let skip = match options.timestamps {
+                TimestampMode::Show => 0,
+                TimestampMode::Strip => timestamp_len,
+                TimestampMode::Relative => {
+                    if let Some(timestamp) = parse_timestamp(stamp) {
+                        let elapsed =
+                            (timestamp - *first_timestamp.get_or_insert(timestamp)) / 1000;
+                        // time-only prefixes wrap around at midnight
+                        let elapsed = elapsed.rem_euclid(24 * 60 * 60);
+                        folded.extra.push_str(&format!(
+                            "[+{}:{:02}:{:02}] ",
+                            elapsed / 3600,
+                            elapsed / 60 % 60,
+                            elapsed % 60
+                        ));
+                    }
+                    timestamp_len
+                }
+            };
+
+            let fold = fold_starts
+                .binary_search(&idx)
+                .ok()
+                .map(|fold| &folds.folds[fold]);
+            // labels replace the line, so they need the timestamp copying in front of them
+            let label = |folded: &mut Self| {
+                if options.timestamps == TimestampMode::Show {
+                    folded.extra.push_str(stamp);
+                }
+            };
+            let (next, shown_skip) = match fold {
+                Some(fold) if options.collapsed.contains(&idx) => {
+                    label(&mut folded);
+                    let hidden = fold.end - fold.start;
+                    folded.extra.push_str(&format!(
+                        "{} {} ({} lines)",
+                        symbols.collapsed, fold.label, hidden
+                    ));
+                    (fold.end + 1, None)
+                }
+                Some(fold) if options.hide_pipeline => {
+                    label(&mut folded);
+                    folded
+                        .extra
+                        .push_str(&format!("{} {}", symbols.expanded, fold.label));
+                    (idx + 1, None)
+                }
+                Some(_) => {
+                    label(&mut folded);
+                    folded.extra.push_str(&format!("{} ", symbols.expanded));
+                    (idx + 1, Some(timestamp_len))
+                }
+                None if options.hide_pipeline && info.pipeline => {
+                    folded.extra.truncate(prefix_start);
+                    idx += 1;
+                    continue;
+                }
+                None => (idx + 1, Some(skip)),
+            };
+            let prefix = &folded.extra[prefix_start..];
+            let width = match shown_skip {
+                Some(0) if prefix.is_empty() => source.line_width(idx),
+                Some(skip) => display_width(prefix) + display_width(&raw[skip..]),
+                None => display_width(prefix),
+            };
+            folded.max_width = folded.max_width.max(width);
+            folded.line_widths.push(width);
+
+            // a collapsed fold is a problem if anything in it is
+            if source.problems()[source.problems().partition_point(|p| *p < idx)..]
+                .first()
+                .is_some_and(|problem| *problem < next)
+            {
+                folded.problems.push(folded.lines.len());
+            }
+            folded.lines.push(ShownLine {
+                original: idx,
+                prefix: prefix_start as u32..folded.extra.len() as u32,
+                skip: shown_skip.map(|skip| skip as u8),
+            });
+            idx = next;
+        }
+        Some(folded)
+    }
+
+    /// shown line `line`, whose text comes from `source`
+    pub fn line<'a>(&'a self, source: &'a Log, line: usize) -> Cow<'a, str> {
+        let shown = &self.lines[line];
+        let prefix = &self.extra[shown.prefix.start as usize..shown.prefix.end as usize];
+        match shown.skip {
+            Some(skip) => {
+                let rest = &source.line(shown.original)[skip as usize..];
+                if prefix.is_empty() {
+                    Cow::Borrowed(rest)
+                } else {
+                    Cow::Owned(format!("{}{}", prefix, rest))
+                }
+            }
+            None => Cow::Borrowed(prefix),
+        }
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// display width of each shown line
+    pub fn line_widths(&self) -> &[u32] {
+        &self.line_widths
+    }
+
+    /// display width of the widest line
+    pub fn max_width(&self) -> u32 {
+        self.max_width
+    }
+
+    /// shown lines with problems on (or in) them
+    pub fn problems(&self) -> &[usize] {
+        &self.problems
+    }
+
+    pub fn wrap_rows(&self) -> &WrapRows {
+        &self.wrap_rows
+    }
+
+    /// the line of the original log that shown line `line` came from
+    pub fn original_line(&self, line: usize) -> usize {
+        self.lines
+            .get(line)
+            .map(|shown| shown.original)
+            .unwrap_or_default()
+    }
+
+    /// the shown line that original line `line` is on (or hidden in, or just after)
+    pub fn shown_line(&self, line: usize) -> usize {
+        self.lines
+            .partition_point(|shown| shown.original <= line)
+            .saturating_sub(1)
+    }
+}
+
+/// the innermost fold that `line` is in
+pub fn fold_at(folds: &[Fold], line: usize) -> Option<&Fold> {
+    folds
+        .iter()
+        .rev()
+        .find(|fold| fold.start <= line && line <= fold.end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PIPELINE: &str = "\
+Started by user me
+[Pipeline] node
+[Pipeline] {
+[Pipeline] stage
+[Pipeline] { (Build)
+make
+[Pipeline] }
+[Pipeline] stage
+[Pipeline] { (Test)
+cargo test
+[Pipeline] }
+[Pipeline] }
+Finished: SUCCESS";
+
+    fn parsed(text: &str) -> (Log, Folds) {
+        let log = Log::from(text.to_string());
+        let mut folds = Folds::default();
+        folds.parse(&log);
+        (log, folds)
+    }
+
+    fn fold(start: usize, end: usize, label: &str) -> Fold {
+        Fold {
+            start,
+            end,
+            label: label.to_string(),
+        }
+    }
+
+    fn shown(log: &Log, folded: &FoldedLog) -> Vec<String> {
+        (0..folded.line_count())
+            .map(|line| folded.line(log, line).into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn finds_nested_folds() {
+        let (_, folds) = parsed(PIPELINE);
+        assert_eq!(
+            folds.folds(),
+            [
+                fold(2, 11, "node"),
+                fold(4, 6, "Build"),
+                fold(8, 10, "Test")
+            ]
+        );
+        assert_eq!(fold_at(folds.folds(), 5), Some(&fold(4, 6, "Build")));
+        assert_eq!(fold_at(folds.folds(), 7), Some(&fold(2, 11, "node")));
+        assert_eq!(fold_at(folds.folds(), 12), None);
+    }
+
+    #[test]
+    fn unterminated_folds_run_to_the_end() {
+        let (_, folds) = parsed("[Pipeline] { (Build)\n[Pipeline] { (Inner)\nmake\nstill going");
+        assert_eq!(folds.folds(), [fold(0, 3, "Build"), fold(1, 3, "Inner")]);
+        // a stray end has nothing to close
+        let (_, folds) = parsed("[Pipeline] }\n[Pipeline] { (Build)\n[Pipeline] }");
+        assert_eq!(folds.folds(), [fold(1, 2, "Build")]);
+    }
+
+    #[test]
+    fn parsing_in_pieces_matches_parsing_at_once() {
+        let (_, whole) = parsed(PIPELINE);
+        for chunk in [1, 3, 7, 20] {
+            let mut log = Log::default();
+            let mut folds = Folds::default();
+            let mut fetched = 0;
+            let bytes = PIPELINE.as_bytes();
+            while fetched < bytes.len() {
+                let end = (fetched + chunk).min(bytes.len());
+                log.append(&PIPELINE[fetched..end], end as u64);
+                fetched = end;
+                folds.parse(&log);
+            }
+            assert_eq!(folds.folds(), whole.folds(), "in chunks of {}", chunk);
+        }
+    }
+
+    #[test]
+    fn unfolded_log_is_shown_as_it_is() {
+        let (log, folds) = parsed("just\nsome output");
+        assert!(FoldedLog::new(&log, &folds, &FoldOptions::default()).is_none());
+    }
+
+    #[test]
+    fn collapsed_fold_maps_back_to_original_lines() {
+        let (log, folds) = parsed(PIPELINE);
+        let options = FoldOptions {
+            collapsed: HashSet::from([4]),
+            ascii: true,
+            ..Default::default()
+        };
+        let folded = FoldedLog::new(&log, &folds, &options).unwrap();
+        let lines = shown(&log, &folded);
+        assert_eq!(lines.len(), 13 - 2);
+        assert_eq!(lines[4], "> Build (2 lines)");
+        assert_eq!(lines[5], "[Pipeline] stage");
+        assert_eq!(folded.original_line(4), 4);
+        assert_eq!(folded.original_line(5), 7);
+        // hidden lines map to the collapsed fold
+        assert_eq!(folded.shown_line(5), 4);
+        assert_eq!(folded.shown_line(7), 5);
+    }
+
+    #[test]
+    fn hiding_pipeline_lines_keeps_labels() {
+        let (log, folds) = parsed(PIPELINE);
+        let options = FoldOptions {
+            hide_pipeline: true,
+            ascii: true,
+            ..Default::default()
+        };
+        let folded = FoldedLog::new(&log, &folds, &options).unwrap();
+        assert_eq!(
+            shown(&log, &folded),
+            [
+                "Started by user me",
+                "v node",
+                "v Build",
+                "make",
+                "v Test",
+                "cargo test",
+                "Finished: SUCCESS"
+            ]
+        );
+        let originals: Vec<usize> = (0..folded.line_count())
+            .map(|line| folded.original_line(line))
+            .collect();
+        assert_eq!(originals, [0, 2, 4, 5, 8, 9, 12]);
+    }
+
+    #[test]
+    fn timestamps_are_stripped_or_made_relative() {
+        let text = "[2024-05-01T10:00:00.000Z] start\n[2024-05-01T11:01:05.500Z] end";
+        let (log, folds) = parsed(text);
+        let strip = FoldOptions {
+            timestamps: TimestampMode::Strip,
+            ..Default::default()
+        };
+        let folded = FoldedLog::new(&log, &folds, &strip).unwrap();
+        assert_eq!(shown(&log, &folded), ["start", "end"]);
+        let relative = FoldOptions {
+            timestamps: TimestampMode::Relative,
+            ..Default::default()
+        };
+        let folded = FoldedLog::new(&log, &folds, &relative).unwrap();
+        assert_eq!(shown(&log, &folded), ["[+0:00:00] start", "[+1:01:05] end"]);
+    }
+}
@@ -1,8 +1,24 @@
 use std::error::Error;
 
-use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::layout::Rect;
 
-use crate::{app::App, event::Event};
+use crate::{
+    app::App,
+    event::Event,
+    jenkins::JenkinsResult,
+    keymap::{Action, KeyChord, Lookup, Scope},
+    palette::{Command, Palette},
+};
+
+/// what came of performing an action
+enum Outcome {
+    /// the action doesn't apply right now, so the keys can do whatever else they're bound to
+    Ignored,
+    Handled,
+    /// handled, with an event to process later
+    Event(Event),
+}
 
 /// handle keydown events. since you may end up needing to do things as a result of a keypress, you
 /// may opt to return an Event that'll be added to the queue for later processing.
@@ -10,78 +26,384 @@ pub async fn handle_key_events(
     key_event: KeyEvent,
     app: &mut App,
 ) -> Result<Option<Event>, Box<dyn Error>> {
-    match key_event.code {
-        // exit the app with esc, q, or <C-c>
-        KeyCode::Esc | KeyCode::Char('q') => app.quit(),
-        KeyCode::Char('c') => {
-            if key_event.modifiers.contains(KeyModifiers::CONTROL) {
-                app.quit();
+    // a search being typed takes every key until it's submitted or cancelled
+    if let Some(prompt) = app.prompt.as_mut() {
+        match key_event.code {
+            KeyCode::Char('c') if key_event.modifiers.contains(KeyModifiers::CONTROL) => app.quit(),
+            KeyCode::Enter => app.submit_search(),
+            KeyCode::Esc => app.prompt = None,
+            KeyCode::Backspace => {
+                prompt.pop();
             }
+            KeyCode::Char(c) => prompt.push(c),
+            _ => {}
         }
+        return Ok(None);
+    }
 
-        // handle going to different panes
-        KeyCode::Char('1') => app.set_active_pane(1),
-        KeyCode::Char('2') => app.set_active_pane(2),
-        KeyCode::Char('3') => app.set_active_pane(3),
-        KeyCode::Char('n') => app.set_active_pane(app.active_pane + 1),
-        KeyCode::Char('p') => app.set_active_pane(app.active_pane - 1),
+    // a build waits on a yes or no before it's started
+    if let Some(trigger) = app.confirm_trigger.take() {
+        if matches!(key_event.code, KeyCode::Char('y' | 'Y')) {
+            return Ok(Some(Event::TriggerBuild(trigger)));
+        }
+        app.set_status("Not starting a build");
+        return Ok(None);
+    }
 
-        // don't do anything else with the other keys?
-        _ => {}
+    if app.help_scroll.is_some() {
+        handle_help_keys(key_event, app);
+        return Ok(None);
+    }
+    if app.palette.is_some() {
+        return Ok(handle_palette_keys(key_event, app));
+    }
+
+    let chord = KeyChord::from(key_event);
+    let scope = Scope::of_pane(app.active_pane);
+    app.pending_keys.push(chord);
+    let actions = loop {
+        match app.keymap.lookup(scope, &app.pending_keys) {
+            Lookup::Pending => return Ok(None),
+            Lookup::Found(actions) => break actions,
+            // a sequence that went nowhere; this key might start another one
+            Lookup::NotFound if app.pending_keys.len() > 1 => app.pending_keys = vec![chord],
+            Lookup::NotFound => break vec![],
+        }
+    };
+    app.pending_keys.clear();
+
+    for action in actions {
+        match perform(action, scope, app) {
+            Outcome::Ignored => continue,
+            Outcome::Handled => break,
+            Outcome::Event(event) => return Ok(Some(event)),
+        }
+    }
+    Ok(None)
+}
+
+/// the help overlay is scrolled with j and k (or the arrows), and any other key closes it
+fn handle_help_keys(key_event: KeyEvent, app: &mut App) {
+    let Some(scroll) = app.help_scroll.as_mut() else {
+        return;
+    };
+    match key_event.code {
+        KeyCode::Char('j') | KeyCode::Down => *scroll = scroll.saturating_add(1),
+        KeyCode::Char('k') | KeyCode::Up => *scroll = scroll.saturating_sub(1),
+        _ => app.help_scroll = None,
     }
+}
 
-    // TODO: figure out if there's a better way to achieve per-pane logic
-    match app.active_pane {
-        1 => match key_event.code {
-            KeyCode::Char('j') | KeyCode::Down => {
-                app.servers.server_state.select_next();
-                return Ok(Some(Event::RefreshJobsForServer));
+/// the command palette takes every key until a command is run or it's closed
+fn handle_palette_keys(key_event: KeyEvent, app: &mut App) -> Option<Event> {
+    let suggestions = app.palette_suggestions();
+    let palette = app.palette.as_mut()?;
+    let ctrl = key_event.modifiers.contains(KeyModifiers::CONTROL);
+    match key_event.code {
+        KeyCode::Char('c') if ctrl => app.quit(),
+        KeyCode::Char('n') if ctrl => palette.select_next(suggestions.len()),
+        KeyCode::Char('p') if ctrl => palette.select_previous(suggestions.len()),
+        KeyCode::Down => palette.select_next(suggestions.len()),
+        KeyCode::Up => palette.select_previous(suggestions.len()),
+        KeyCode::Tab => palette.complete(&suggestions),
+        KeyCode::Backspace => palette.pop(),
+        KeyCode::Char(c) => palette.push(c),
+        KeyCode::Esc => app.palette = None,
+        KeyCode::Enter => match palette.command(&suggestions) {
+            Ok(command) => {
+                app.palette = None;
+                return run_command(command, app);
             }
-            KeyCode::Char('k') | KeyCode::Up => {
-                app.servers.server_state.select_previous();
-                return Ok(Some(Event::RefreshJobsForServer));
+            // it might just need an argument
+            Err(e) => {
+                palette.complete(&suggestions);
+                app.set_status(&e);
             }
-            KeyCode::Char('r') => app.refresh_servers(),
-            _ => {}
         },
-        2 => {
-            if let Some((stateful_job, _)) = app.get_current_server_jobs() {
-                match key_event.code {
-                    // TODO: figure out if we should _not_ refresh logs if there is _already_ logs,
-                    // unless the user explicitly requests it
-                    KeyCode::Char('j') | KeyCode::Up => {
-                        stateful_job.job_state.select_next();
-                        return Ok(Some(Event::RefreshLogsForJob));
+        _ => {}
+    }
+    None
+}
+
+/// run a command from the palette
+fn run_command(command: Command, app: &mut App) -> Option<Event> {
+    match command {
+        Command::Action(action) => match perform(action, Scope::of_pane(app.active_pane), app) {
+            Outcome::Event(event) => return Some(event),
+            Outcome::Ignored | Outcome::Handled => {}
+        },
+        Command::RefreshAll => return Some(Event::RefreshAllJobs),
+        Command::TriggerBuild(job) => app.confirm_trigger_build(job),
+        Command::Server(name) => {
+            if app.select_server(&name) {
+                return Some(Event::RefreshJobsForServer);
+            }
+        }
+        Command::Job(name) => {
+            if app.select_job(&name) {
+                return Some(Event::RefreshLogsForJob);
+            }
+        }
+    }
+    None
+}
+
+/// perform `action` the way it's done in `scope`
+fn perform(action: Action, scope: Scope, app: &mut App) -> Outcome {
+    match scope {
+        Scope::Servers => perform_servers(action, app),
+        Scope::Jobs => perform_jobs(action, app),
+        Scope::Logs => perform_logs(action, app),
+        Scope::Global => perform_global(action, app),
+    }
+}
+
+/// actions that do the same thing whichever pane is active
+fn perform_global(action: Action, app: &mut App) -> Outcome {
+    match action {
+        Action::Quit => app.quit(),
+        Action::Help => app.help_scroll = Some(0),
+        Action::CommandPalette => app.palette = Some(Palette::default()),
+        Action::FocusServers if app.servers_hidden() => {
+            app.set_status("The server list is hidden, since there's only one server")
+        }
+        Action::FocusServers => app.set_active_pane(1),
+        Action::FocusJobs => app.set_active_pane(2),
+        Action::FocusLogs => app.set_active_pane(3),
+        Action::NextPane => app.set_active_pane(app.active_pane + 1),
+        Action::PreviousPane => app.set_active_pane(app.active_pane - 1),
+        Action::ToggleMouse => return Outcome::Event(Event::ToggleMouseCapture),
+        Action::GrowPane => app.resize_pane(true),
+        Action::ShrinkPane => app.resize_pane(false),
+        Action::ToggleZoom => app.toggle_zoom(),
+        Action::CycleLayout => app.cycle_layout(),
+        _ => return Outcome::Ignored,
+    }
+    Outcome::Handled
+}
+
+fn perform_servers(action: Action, app: &mut App) -> Outcome {
+    let last = app.server_list_len().saturating_sub(1);
+    let state = &mut app.servers.server_state;
+    match action {
+        Action::Down => state.select_next(),
+        Action::Up => state.select_previous(),
+        Action::Top => state.select_first(),
+        Action::Bottom => state.select(Some(last)),
+        Action::Refresh => {
+            app.refresh_servers();
+            return app
+                .select_only_server()
+                .map_or(Outcome::Handled, Outcome::Event);
+        }
+        _ => return perform_global(action, app),
+    }
+    Outcome::Event(Event::RefreshJobsForServer)
+}
+
+fn perform_jobs(action: Action, app: &mut App) -> Outcome {
+    let Some(stateful_jobs) = app.get_current_server_jobs() else {
+        return perform_global(action, app);
+    };
+    let last = stateful_jobs.jobs.len().saturating_sub(1);
+    let state = &mut stateful_jobs.job_state;
+    match action {
+        // finished builds' logs are only fetched once, so this is cheap
+        Action::Down => state.select_next(),
+        Action::Up => state.select_previous(),
+        Action::Top => state.select_first(),
+        Action::Bottom => state.select(Some(last)),
+        // TODO: should this refresh logs too?
+        Action::Refresh => return Outcome::Event(Event::RefreshJobsForServer),
+        Action::ToggleMark => app.toggle_mark(),
+        Action::ToggleDiff => {
+            if let Some(event) = app.toggle_diff() {
+                return Outcome::Event(event);
+            }
+        }
+        Action::CopyLink => app.copy_link(|job| job.link.clone()),
+        Action::CopyJobLink => app.copy_link(JenkinsResult::job_link),
+        Action::OpenBuild => app.open_link(|job| job.link.clone()),
+        Action::OpenJob => app.open_link(JenkinsResult::job_link),
+        Action::ExportCsv => app.export_jobs(false),
+        Action::ExportMarkdown => app.export_jobs(true),
+        Action::CycleSort => app.cycle_job_sort(),
+        Action::ReverseSort => app.reverse_job_sort(),
+        _ => return perform_global(action, app),
+    }
+    match action {
+        Action::Down | Action::Up | Action::Top | Action::Bottom => {
+            Outcome::Event(Event::RefreshLogsForJob)
+        }
+        _ => Outcome::Handled,
+    }
+}
+
+fn perform_logs(action: Action, app: &mut App) -> Outcome {
+    let has_job = app
+        .get_current_server_jobs()
+        .is_some_and(|stateful_jobs| stateful_jobs.job_state.selected().is_some());
+    if !has_job {
+        return perform_global(action, app);
+    }
+    // the cursor moves instead of the logs scrolling, if it's shown
+    let cursor = app.log_scroll_state.cursor().is_some();
+    let page = app.log_scroll_state.page_rows() as isize;
+    match action {
+        Action::Down if cursor => app.move_cursor(1),
+        Action::Up if cursor => app.move_cursor(-1),
+        Action::PageDown if cursor => app.move_cursor(page),
+        Action::PageUp if cursor => app.move_cursor(-page),
+        Action::Top if cursor => app.move_cursor(isize::MIN),
+        Action::Bottom if cursor => app.move_cursor(isize::MAX),
+        Action::Down => app.log_scroll_state.scroll_down(),
+        Action::Up => app.log_scroll_state.scroll_up(),
+        Action::Left => app.log_scroll_state.scroll_left(),
+        Action::Right => app.log_scroll_state.scroll_right(),
+        Action::PageDown => app.log_scroll_state.scroll_page_down(),
+        Action::PageUp => app.log_scroll_state.scroll_page_up(),
+        Action::Top => app.log_scroll_state.scroll_to_top(),
+        Action::Bottom => app.log_scroll_state.scroll_to_bottom(),
+        Action::ToggleWrap => app.wrap_logs = !app.wrap_logs,
+        Action::LoadFullLogs => return Outcome::Event(Event::LoadFullLogsForJob),
+        // in a diff, jump between changes instead
+        Action::NextProblem if app.diff.is_some() => app.jump_to_change(true),
+        Action::PreviousProblem if app.diff.is_some() => app.jump_to_change(false),
+        Action::NextProblem => app.jump_to_problem(true),
+        Action::PreviousProblem => app.jump_to_problem(false),
+        Action::ToggleProblems => app.show_problems = !app.show_problems,
+        Action::ToggleDiff => {
+            if let Some(event) = app.toggle_diff() {
+                return Outcome::Event(event);
+            }
+        }
+        Action::ToggleFold => app.toggle_fold(),
+        Action::ToggleAllFolds => app.toggle_all_folds(),
+        Action::TogglePipeline => app.toggle_hide_pipeline(),
+        Action::CycleTimestamps => app.cycle_timestamps(),
+        Action::CopyLines => app.copy_logs(false),
+        Action::CopyLog => app.copy_logs(true),
+        Action::OpenConsole => app.open_link(JenkinsResult::console_link),
+        Action::SaveLog => app.save_logs(false),
+        Action::SaveStrippedLog => app.save_logs(true),
+        Action::PageLogs => return Outcome::Event(Event::PageLogs),
+        Action::EditLogs => return Outcome::Event(Event::EditLogs),
+        Action::ToggleSelection => app.toggle_selection(),
+        Action::ToggleLineNumbers => app.toggle_line_numbers(),
+        Action::Search => app.start_search(),
+        Action::NextMatch if app.search.is_some() => app.jump_to_match(true),
+        Action::PreviousMatch if app.search.is_some() => app.jump_to_match(false),
+        Action::NextMatch | Action::PreviousMatch => return Outcome::Ignored,
+        Action::Back => {
+            if !app.back_out_of_logs() {
+                return Outcome::Ignored;
+            }
+        }
+        _ => return perform_global(action, app),
+    }
+    Outcome::Handled
+}
+
+/// rows the mouse wheel scrolls the logs by
+const WHEEL_ROWS: usize = 3;
+
+/// the item of a bordered list in `area`, scrolled past `offset` items, that's on screen row
+/// `row`, if there is one
+fn list_row(area: Rect, offset: usize, row: u16, len: usize) -> Option<usize> {
+    // skip the top border
+    let row = row.checked_sub(area.y + 1)?;
+    let idx = offset + row as usize;
+    (row + 2 < area.height && idx < len).then_some(idx)
+}
+
+/// handle mouse events: clicking a pane focuses it, clicking a list row selects it, the wheel
+/// scrolls, and dragging the borders between panes resizes them
+pub async fn handle_mouse_events(
+    mouse_event: MouseEvent,
+    app: &mut App,
+) -> Result<Option<Event>, Box<dyn Error>> {
+    let MouseEvent { column, row, .. } = mouse_event;
+    match mouse_event.kind {
+        MouseEventKind::Down(MouseButton::Left) => {
+            if let Some(border) = app.border_at(column, row) {
+                app.dragging = Some(border);
+                return Ok(None);
+            }
+            let Some(pane) = app.pane_at(column, row) else {
+                return Ok(None);
+            };
+            if pane != app.active_pane {
+                app.set_active_pane(pane);
+            }
+            match pane {
+                1 => {
+                    let len = app.server_list_len();
+                    // clicking the server the wheel stopped on fetches it without waiting
+                    let wheeled = app.server_wheeled();
+                    let state = &mut app.servers.server_state;
+                    if let Some(idx) = list_row(app.areas.server_list, state.offset(), row, len) {
+                        if state.selected() != Some(idx) || wheeled {
+                            state.select(Some(idx));
+                            return Ok(Some(Event::RefreshJobsForServer));
+                        }
                     }
-                    KeyCode::Char('k') | KeyCode::Down => {
-                        stateful_job.job_state.select_previous();
-                        return Ok(Some(Event::RefreshLogsForJob));
+                }
+                2 => {
+                    // the table's header takes up the first row
+                    let mut area = app.areas.job_list;
+                    area.y += 1;
+                    area.height = area.height.saturating_sub(1);
+                    // clicking the build the wheel stopped on fetches it without waiting
+                    let wheeled = app.job_wheeled();
+                    if let Some(stateful_jobs) = app.get_current_server_jobs() {
+                        let state = &mut stateful_jobs.job_state;
+                        let len = stateful_jobs.jobs.len();
+                        if let Some(idx) = list_row(area, state.offset(), row, len) {
+                            if state.selected() != Some(idx) || wheeled {
+                                state.select(Some(idx));
+                                return Ok(Some(Event::RefreshLogsForJob));
+                            }
+                        }
                     }
-                    // TODO: should this refresh logs too?
-                    KeyCode::Char('r') => return Ok(Some(Event::RefreshJobsForServer)),
-                    _ => {}
                 }
+                _ => {}
             }
         }
-        3 => {
-            if let Some((stateful_job, _)) = app.get_current_server_jobs() {
-                if stateful_job.job_state.selected().is_some() {
-                    match key_event.code {
-                        KeyCode::Char('j') | KeyCode::Down => app.log_scroll_state.scroll_down(),
-                        KeyCode::Char('k') | KeyCode::Up => app.log_scroll_state.scroll_up(),
-                        KeyCode::Char('h') | KeyCode::Left => app.log_scroll_state.scroll_left(),
-                        KeyCode::Char('l') | KeyCode::Right => app.log_scroll_state.scroll_right(),
-                        KeyCode::Char('w') => app.wrap_logs = !app.wrap_logs,
-                        KeyCode::PageDown => app.log_scroll_state.scroll_page_down(),
-                        KeyCode::PageUp => app.log_scroll_state.scroll_page_up(),
-                        _ => {}
+        MouseEventKind::Drag(MouseButton::Left) => {
+            if let Some(border) = app.dragging {
+                app.drag_border(border, column, row);
+            }
+        }
+        MouseEventKind::Up(MouseButton::Left) => app.dragging = None,
+        // the wheel scrolls whatever's under the pointer, focused or not
+        MouseEventKind::ScrollDown | MouseEventKind::ScrollUp => {
+            let down = mouse_event.kind == MouseEventKind::ScrollDown;
+            match app.pane_at(column, row) {
+                Some(1) => app.wheel_servers(down),
+                Some(2) => app.wheel_jobs(down),
+                Some(3) => {
+                    for _ in 0..WHEEL_ROWS {
+                        if down {
+                            app.log_scroll_state.scroll_down();
+                        } else {
+                            app.log_scroll_state.scroll_up();
+                        }
                     }
                 }
+                _ => {}
+            }
+        }
+        MouseEventKind::ScrollRight if app.pane_at(column, row) == Some(3) => {
+            for _ in 0..WHEEL_ROWS {
+                app.log_scroll_state.scroll_right();
+            }
+        }
+        MouseEventKind::ScrollLeft if app.pane_at(column, row) == Some(3) => {
+            for _ in 0..WHEEL_ROWS {
+                app.log_scroll_state.scroll_left();
             }
         }
-        // nothing else to do I suppose
         _ => {}
     }
-
     Ok(None)
 }
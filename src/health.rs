@@ -0,0 +1,245 @@
+use std::{collections::BTreeMap, fmt::Display, time::Duration};
+
+use chrono::{DateTime, Utc};
+use reqwest::{header::RETRY_AFTER, Response, StatusCode};
+
+use crate::jenkins::parse_seconds;
+
+/// how hard to try before giving up on a request
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// how many times to retry after the first attempt
+    pub max_retries: u32,
+    /// how long to wait before the first retry; doubles every retry after that
+    pub initial_backoff: Duration,
+    /// the most we'll ever wait between attempts, even if the server asks for longer
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// read `retries`, `retry_backoff`, and `max_retry_backoff` from a server's config
+    pub fn from_config(value: &BTreeMap<String, String>) -> Result<Self, String> {
+        let mut policy = Self::default();
+        if let Some(retries) = value.get("retries") {
+            policy.max_retries = retries
+                .parse()
+                .map_err(|_| format!("retries is not a number: {}", retries))?;
+        }
+        if let Some(backoff) = value.get("retry_backoff") {
+            policy.initial_backoff = parse_seconds("retry_backoff", backoff)?;
+        }
+        if let Some(backoff) = value.get("max_retry_backoff") {
+            policy.max_backoff = parse_seconds("max_retry_backoff", backoff)?;
+        }
+        Ok(policy)
+    }
+
+    /// how long to wait before retrying for the `attempt`th time (starting at 0), preferring
+    /// the server's `Retry-After` if it sent one
+    pub fn backoff(&self, attempt: u32, retry_after: Option<Duration>) -> Duration {
+        retry_after
+            .unwrap_or_else(|| {
+                self.initial_backoff
+                    .saturating_mul(2u32.saturating_pow(attempt))
+            })
+            .min(self.max_backoff)
+    }
+}
+
+/// if the response is worth retrying, how long the server asked us to wait (if it did). server
+/// errors and 429s mean "try again later" rather than "this will never work".
+pub fn should_retry(response: &Response) -> Option<Option<Duration>> {
+    let status = response.status();
+    if !status.is_server_error() && status != StatusCode::TOO_MANY_REQUESTS {
+        return None;
+    }
+    // Retry-After is either a number of seconds or an HTTP date
+    let retry_after = response
+        .headers()
+        .get(RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| {
+            v.parse::<u64>().map(Duration::from_secs).ok().or_else(|| {
+                let date = DateTime::parse_from_rfc2822(v).ok()?;
+                (date.with_timezone(&Utc) - Utc::now()).to_std().ok()
+            })
+        });
+    Some(retry_after)
+}
+
+/// how a server has been behaving lately
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum HealthState {
+    /// haven't talked to it yet
+    #[default]
+    Unknown,
+    Ok,
+    /// answered, but only after retrying, or with a server error
+    Degraded,
+    /// couldn't get an answer at all
+    Unreachable,
+    /// answered with 401/403
+    AuthFailed,
+}
+
+impl Display for HealthState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            HealthState::Unknown => "?",
+            HealthState::Ok => "ok",
+            HealthState::Degraded => "degraded",
+            HealthState::Unreachable => "unreachable",
+            HealthState::AuthFailed => "auth failed",
+        })
+    }
+}
+
+/// the health of a server, as of its last request
+#[derive(Debug, Clone, Default)]
+pub struct ServerHealth {
+    pub state: HealthState,
+    /// what went wrong last time, if anything did
+    pub last_error: Option<String>,
+    /// how long the last successful attempt took
+    pub latency: Option<Duration>,
+}
+
+impl ServerHealth {
+    /// record the outcome of a request that took `retries` retries
+    pub fn update(
+        &mut self,
+        outcome: &Result<Response, reqwest::Error>,
+        retries: u32,
+        latency: Duration,
+    ) {
+        match outcome {
+            Ok(response) => {
+                let status = response.status();
+                self.latency = Some(latency);
+                (self.state, self.last_error) =
+                    if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+                        (HealthState::AuthFailed, Some(format!("HTTP {}", status)))
+                    } else if status.is_server_error() {
+                        (HealthState::Degraded, Some(format!("HTTP {}", status)))
+                    } else if retries > 0 {
+                        (
+                            HealthState::Degraded,
+                            Some(format!("needed {} retries", retries)),
+                        )
+                    } else {
+                        (HealthState::Ok, None)
+                    };
+            }
+            Err(e) => {
+                self.state = HealthState::Unreachable;
+                self.last_error = Some(e.to_string());
+            }
+        }
+    }
+}
+
+impl Display for ServerHealth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.state, self.latency, &self.last_error) {
+            (HealthState::Ok, Some(latency), _) => write!(f, "ok {}ms", latency.as_millis()),
+            (state, _, Some(last_error)) => write!(f, "{}: {}", state, last_error),
+            (state, _, None) => state.fmt(f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(status: u16, retry_after: Option<&str>) -> Response {
+        let mut builder = http::Response::builder().status(status);
+        if let Some(retry_after) = retry_after {
+            builder = builder.header(RETRY_AFTER, retry_after);
+        }
+        builder.body("").unwrap().into()
+    }
+
+    #[test]
+    fn only_transient_errors_are_retried() {
+        assert_eq!(should_retry(&response(200, None)), None);
+        assert_eq!(should_retry(&response(404, Some("5"))), None);
+        assert_eq!(should_retry(&response(401, None)), None);
+        assert_eq!(should_retry(&response(503, None)), Some(None));
+        assert_eq!(should_retry(&response(429, None)), Some(None));
+    }
+
+    #[test]
+    fn parses_retry_after_seconds() {
+        assert_eq!(
+            should_retry(&response(429, Some("120"))),
+            Some(Some(Duration::from_secs(120)))
+        );
+        assert_eq!(should_retry(&response(503, Some("soon"))), Some(None));
+    }
+
+    #[test]
+    fn parses_retry_after_dates() {
+        let later = (Utc::now() + chrono::Duration::seconds(60)).to_rfc2822();
+        let Some(Some(wait)) = should_retry(&response(503, Some(&later))) else {
+            panic!("no Retry-After from {}", later);
+        };
+        assert!(
+            wait > Duration::from_secs(55) && wait <= Duration::from_secs(60),
+            "{:?}",
+            wait
+        );
+        // a date that's already gone means there's no need to wait
+        let earlier = (Utc::now() - chrono::Duration::seconds(60)).to_rfc2822();
+        assert_eq!(should_retry(&response(503, Some(&earlier))), Some(None));
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let policy = RetryPolicy::default();
+        let waits: Vec<Duration> = (0..8)
+            .map(|attempt| policy.backoff(attempt, None))
+            .collect();
+        assert_eq!(
+            waits,
+            [500, 1000, 2000, 4000, 8000, 16000, 30000, 30000].map(Duration::from_millis)
+        );
+        // far too many attempts to compute doesn't overflow
+        assert_eq!(policy.backoff(u32::MAX, None), policy.max_backoff);
+    }
+
+    #[test]
+    fn backoff_prefers_retry_after_but_caps_it() {
+        let policy = RetryPolicy::default();
+        let asked = Some(Duration::from_secs(7));
+        assert_eq!(policy.backoff(0, asked), Duration::from_secs(7));
+        assert_eq!(policy.backoff(5, asked), Duration::from_secs(7));
+        let too_long = Some(Duration::from_secs(3600));
+        assert_eq!(policy.backoff(0, too_long), policy.max_backoff);
+    }
+
+    #[test]
+    fn reads_policy_from_config() {
+        let config = BTreeMap::from([
+            ("retries".to_string(), "5".to_string()),
+            ("retry_backoff".to_string(), "0.25".to_string()),
+            ("max_retry_backoff".to_string(), "2".to_string()),
+        ]);
+        let policy = RetryPolicy::from_config(&config).unwrap();
+        assert_eq!(policy.max_retries, 5);
+        assert_eq!(policy.backoff(0, None), Duration::from_millis(250));
+        assert_eq!(policy.backoff(4, None), Duration::from_secs(2));
+        let bad = BTreeMap::from([("retries".to_string(), "lots".to_string())]);
+        assert!(RetryPolicy::from_config(&bad).is_err());
+    }
+}
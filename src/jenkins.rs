@@ -1,36 +1,64 @@
 use std::{
-    collections::BTreeMap, error::Error, fmt::Display, fs, path::Path, str::FromStr, sync::LazyLock,
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fmt::Display,
+    fs,
+    path::Path,
+    str::FromStr,
+    sync::{Arc, LazyLock},
+    time::{Duration, Instant},
 };
 
 use atom_syndication::{Entry, Feed};
-use chrono::{DateTime, FixedOffset};
-use ratatui::{style::Color, widgets::ListItem};
+use chrono::{DateTime, FixedOffset, Utc};
+use expanduser::expanduser;
 use regex::Regex;
-use reqwest::{Client, Response, Url};
-use serde::Deserialize;
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue, RANGE},
+    Certificate, Client, Identity, Method, Proxy, Response, StatusCode, Url,
+};
+use serde::{Deserialize, Serialize};
+use tokio::{sync::Semaphore, time::sleep};
+
+use crate::{
+    causes::FoundCause,
+    credentials::{resolve_credentials, Password, Secret},
+    health::{should_retry, HealthState, RetryPolicy, ServerHealth},
+    log_store::LogLimit,
+    log_view::Log,
+    record::{self, HttpMode},
+};
 
 /// describes the state of a build
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum BuildState {
     Success,
     Failure,
+    /// still running, as far as we know
     Unknown,
+    Aborted,
+    NotBuilt,
 }
 
 impl FromStr for BuildState {
     type Err = String;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        return match s.to_lowercase().as_str() {
+        match s.to_lowercase().as_str() {
             "stable" => Ok(BuildState::Success),
             "back to normal" => Ok(BuildState::Success),
-            "broken" => Ok(BuildState::Failure), // TODO: is this correct?
+            // failed builds are "broken since this build" or "broken since build #n"
+            "broken" => Ok(BuildState::Failure),
+            "aborted" => Ok(BuildState::Aborted),
+            "not built" => Ok(BuildState::NotBuilt),
+            // including "?", which is what the feed calls a build that's still running
             _ => Ok(BuildState::Unknown),
-        };
+        }
     }
 }
 
 /// describes a build
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct JenkinsResult {
     /// job name
     pub name: String,
@@ -43,13 +71,59 @@ pub struct JenkinsResult {
     /// URL to job
     pub link: Url,
     /// job logs (might have a lot)
-    pub logs: String,
+    #[serde(skip)]
+    pub logs: Log,
+    /// known failure causes found in the logs
+    #[serde(default)]
+    pub causes: Vec<FoundCause>,
+    /// when it started and how long it took, if the JSON API said
+    #[serde(default)]
+    pub timing: Option<BuildTiming>,
+    /// name of the server it's from, in the all-servers view
+    #[serde(skip)]
+    pub server: Option<String>,
+    /// the logs were fetched while the build was running and it's finished since, so they may
+    /// be missing whatever it wrote last
+    #[serde(skip)]
+    pub logs_unfinished: bool,
+}
+
+/// when a build started and how long it took (or should take), which the RSS feed doesn't say
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildTiming {
+    pub started: DateTime<Utc>,
+    /// how long it took; zero while it's still running
+    pub duration: Duration,
+    /// how long Jenkins expects it to take, going by previous builds
+    pub estimated: Option<Duration>,
+    pub building: bool,
+}
+
+impl BuildTiming {
+    /// how long it took, or has taken so far
+    pub fn elapsed(&self, now: DateTime<Utc>) -> Duration {
+        if self.building {
+            (now - self.started).to_std().unwrap_or_default()
+        } else {
+            self.duration
+        }
+    }
+
+    /// how much longer it's expected to take, if it's still running. past its estimate, that's
+    /// negative.
+    pub fn remaining(&self, now: DateTime<Utc>) -> Option<chrono::Duration> {
+        if !self.building {
+            return None;
+        }
+        let estimated = chrono::Duration::from_std(self.estimated?).ok()?;
+        Some(self.started + estimated - now)
+    }
 }
 
 /// How to parse the rss entry's title to get build name, number, and status
 static BUILD_REGEX: LazyLock<Regex> = LazyLock::new(|| {
     Regex::new(
-        r"(?P<name>.*) #(?P<build_number>\d+) \((?P<build_state>stable|broken|back to normal).*",
+        r"(?P<name>.*) #(?P<build_number>\d+) \((?P<build_state>stable|broken|back to normal|aborted|not built|\?).*",
     )
     .unwrap()
 });
@@ -62,7 +136,11 @@ impl Default for JenkinsResult {
             build_state: BuildState::Failure,
             updated: DateTime::UNIX_EPOCH.fixed_offset(),
             link: Url::from_file_path("/dev/null").unwrap(),
-            logs: String::new(),
+            logs: Log::default(),
+            causes: vec![],
+            timing: None,
+            server: None,
+            logs_unfinished: false,
         }
     }
 }
@@ -90,7 +168,7 @@ impl TryFrom<Entry> for JenkinsResult {
                 .as_str()
                 .parse()?,
             updated: value.updated,
-            link: Url::parse(&value.links[0].href)?,
+            link: Url::parse(&value.links.first().ok_or("Entry has no link")?.href)?,
 
             // TODO: figure out if hydrating the logs now would be too expensive
             ..Default::default()
@@ -98,15 +176,26 @@ impl TryFrom<Entry> for JenkinsResult {
     }
 }
 
-impl<'a> From<JenkinsResult> for ListItem<'a> {
-    fn from(value: JenkinsResult) -> Self {
-        Self::new(format!("{} #{}", value.name, value.build_number)).style(
-            match value.build_state {
-                BuildState::Success => Color::Green,
-                BuildState::Failure => Color::Red,
-                _ => Color::Yellow,
-            },
-        )
+impl JenkinsResult {
+    /// the job this is a build of
+    pub fn job_link(&self) -> Url {
+        self.link.join("..").unwrap_or_else(|_| self.link.clone())
+    }
+
+    /// the build's console output
+    pub fn console_link(&self) -> Url {
+        self.link
+            .join("console")
+            .unwrap_or_else(|_| self.link.clone())
+    }
+
+    /// carry over the logs already loaded for the same build from before it was re-fetched,
+    /// noting if it's finished since they were
+    pub fn take_logs(&mut self, old: &mut JenkinsResult) {
+        self.logs = std::mem::take(&mut old.logs);
+        self.logs_unfinished = !self.logs.is_empty()
+            && self.build_state != BuildState::Unknown
+            && (old.build_state == BuildState::Unknown || old.logs_unfinished);
     }
 }
 
@@ -116,53 +205,308 @@ impl Display for JenkinsResult {
     }
 }
 
-impl PartialOrd for JenkinsResult {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.cmp(other))
+/// what the job list is sorted by
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortColumn {
+    /// when the build was last updated
+    #[default]
+    Updated,
+    Name,
+    Build,
+    State,
+    Duration,
+}
+
+impl SortColumn {
+    pub fn next(self) -> Self {
+        match self {
+            SortColumn::Updated => SortColumn::Name,
+            SortColumn::Name => SortColumn::Build,
+            SortColumn::Build => SortColumn::State,
+            SortColumn::State => SortColumn::Duration,
+            SortColumn::Duration => SortColumn::Updated,
+        }
+    }
+
+    /// whether it naturally sorts from the most to the least: newest, highest, worst, and longest
+    /// first, but names from A to Z
+    pub fn descending(self) -> bool {
+        self != SortColumn::Name
+    }
+}
+
+impl Display for SortColumn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SortColumn::Updated => "age",
+            SortColumn::Name => "job",
+            SortColumn::Build => "build number",
+            SortColumn::State => "state",
+            SortColumn::Duration => "duration",
+        })
     }
 }
 
-// TODO: maybe sort by build number?
-impl Ord for JenkinsResult {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.updated.cmp(&other.updated)
+/// how bad a state is, for sorting the worst first
+fn state_rank(state: &BuildState) -> u8 {
+    match state {
+        BuildState::Failure => 0,
+        BuildState::Unknown => 1,
+        BuildState::Aborted => 2,
+        BuildState::NotBuilt => 3,
+        BuildState::Success => 4,
     }
 }
 
+/// how the job list is sorted: by a column, in its natural order or reversed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct JobSort {
+    pub column: SortColumn,
+    pub reversed: bool,
+}
+
+impl JobSort {
+    /// compare two builds; ties go to the most recently updated
+    pub fn compare(&self, a: &JenkinsResult, b: &JenkinsResult, now: DateTime<Utc>) -> Ordering {
+        let duration = |job: &JenkinsResult| job.timing.map(|timing| timing.elapsed(now));
+        let ordering = match self.column {
+            SortColumn::Updated => Ordering::Equal,
+            SortColumn::Name => a.name.cmp(&b.name),
+            SortColumn::Build => b.build_number.cmp(&a.build_number),
+            SortColumn::State => state_rank(&a.build_state).cmp(&state_rank(&b.build_state)),
+            SortColumn::Duration => duration(b).cmp(&duration(a)),
+        }
+        .then_with(|| b.updated.cmp(&a.updated));
+        if self.reversed {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+}
+
+/// MiB, for telling the user how much of a log they're looking at
+fn mib(bytes: u64) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
 impl JenkinsResult {
     /// hydrate logs on individual results one at a time, because getting them all at once might be
-    /// really expensive.
-    pub async fn hydrate_logs(&mut self, server: &mut JenkinsServer) -> Result<(), Box<dyn Error>> {
-        let logs_url = self.link.join("consoleText").unwrap();
-        self.logs = server
-            .request_with_auth(logs_url.as_str())
-            .await?
-            .text()
-            .await?;
+    /// really expensive
+    pub async fn hydrate_logs(
+        &mut self,
+        server: &mut JenkinsServer,
+        limit: LogLimit,
+    ) -> Result<(), Box<dyn Error>> {
+        self.logs = fetch_logs(server, &self.link, limit).await?;
         Ok(())
     }
 }
 
-// TODO: does it make sense to store the jobs in here too?
-/// how to connect to jenkins
-#[derive(Debug, Deserialize)]
+/// fetch the logs of the build at `link`. logs are streamed in, so only as much as `limit`
+/// allows is ever held in memory.
+pub async fn fetch_logs(
+    server: &mut JenkinsServer,
+    link: &Url,
+    limit: LogLimit,
+) -> Result<Log, Box<dyn Error>> {
+    let logs_url = link.join("consoleText").unwrap();
+    let mut headers = HeaderMap::new();
+    if let LogLimit::Tail(max) = limit {
+        // if the server honors this, it only sends the tail; if not, the tail is kept below
+        headers.insert(RANGE, format!("bytes=-{}", max).parse()?);
+    }
+    let mut response = server
+        .request_with_headers(logs_url.as_str(), headers)
+        .await?;
+    let partial = response.status() == StatusCode::PARTIAL_CONTENT;
+
+    let mut bytes: Vec<u8> = vec![];
+    let mut fetched_bytes: u64 = 0;
+    let mut dropped_bytes: u64 = 0;
+    while let Some(chunk) = response.chunk().await? {
+        fetched_bytes += chunk.len() as u64;
+        bytes.extend_from_slice(&chunk);
+        match limit {
+            LogLimit::Head(max) if bytes.len() as u64 >= max => {
+                bytes.truncate(max as usize);
+                break;
+            }
+            // only shuffle bytes around once there's plenty to drop
+            LogLimit::Tail(max) if bytes.len() as u64 > max * 2 => {
+                let excess = bytes.len() - max as usize;
+                bytes.drain(..excess);
+                dropped_bytes += excess as u64;
+            }
+            _ => {}
+        }
+    }
+    if let LogLimit::Tail(max) = limit {
+        let excess = bytes.len().saturating_sub(max as usize);
+        bytes.drain(..excess);
+        dropped_bytes += excess as u64;
+    }
+
+    let text = String::from_utf8_lossy(&bytes).into_owned();
+    Ok(match limit {
+        LogLimit::Head(max) if fetched_bytes >= max => Log::truncated(
+            text,
+            &format!(
+                "[jenkins-atom-tui: only showing the first {:.1} MiB of this log; press L to load all of it]",
+                mib(max)
+            ),
+            fetched_bytes,
+        ),
+        LogLimit::Tail(_) if dropped_bytes > 0 || partial => Log::truncated(
+            text,
+            &format!(
+                "[jenkins-atom-tui: only showing the last {:.1} MiB of this log; press L to load all of it]",
+                mib(bytes.len() as u64)
+            ),
+            // a range response doesn't tell us how much came before it; the next refresh
+            // will just start over
+            if partial { 0 } else { fetched_bytes },
+        ),
+        _ => text.into(),
+    })
+}
+
+/// fetch whatever's been logged since `start` bytes into the build at `link` with
+/// `progressiveText`, for builds that are still running. returns it along with where the next
+/// fetch should start from.
+pub async fn fetch_new_logs(
+    server: &mut JenkinsServer,
+    link: &Url,
+    start: u64,
+) -> Result<(String, u64), Box<dyn Error>> {
+    let logs_url = link.join(&format!("logText/progressiveText?start={}", start))?;
+    let response = server.request_with_auth(logs_url.as_str()).await?;
+    // where the next fetch should start from
+    let next_start = response
+        .headers()
+        .get("X-Text-Size")
+        .and_then(|v| v.to_str().ok()?.parse().ok());
+    let more = response.text().await?;
+    let next_start = next_start.unwrap_or(start + more.len() as u64);
+    Ok((more, next_start))
+}
+
+/// JJB sections like `[plugin "hipchat"]` configure plugins rather than a Jenkins server
+static PLUGIN_SECTION_PREFIX: &str = "plugin ";
+
+/// the JJB section whose keys every other server section inherits
+pub static DEFAULT_SECTION: &str = "jenkins";
+
+/// parse a JJB boolean, which is python's `configparser` flavor of boolean
+fn parse_ini_bool(key: &str, value: &str) -> Result<bool, String> {
+    match value.to_lowercase().as_str() {
+        "1" | "yes" | "true" | "on" => Ok(true),
+        "0" | "no" | "false" | "off" => Ok(false),
+        _ => Err(format!("{} is not a boolean: {}", key, value)),
+    }
+}
+
+/// parse a (possibly fractional) number of seconds
+pub fn parse_seconds(key: &str, value: &str) -> Result<Duration, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .and_then(|t| Duration::try_from_secs_f64(t).ok())
+        .ok_or(format!("{} is not a number of seconds: {}", key, value))
+}
+
+/// read a file referenced by a config key
+fn read_config_file(key: &str, path: &str) -> Result<Vec<u8>, String> {
+    let path = expanduser(path).map_err(|e| format!("{}: {}", key, e))?;
+    fs::read(&path).map_err(|e| format!("Cannot read {} {}: {}", key, path.display(), e))
+}
+
+/// build the HTTP client for a server from its TLS, proxy, and timeout settings
+fn build_client(value: &BTreeMap<String, String>) -> Result<Client, String> {
+    // crumbs only work with the session cookie they were handed out with
+    let mut builder = Client::builder().cookie_store(true);
+    if let Some(timeout) = value.get("timeout") {
+        builder = builder.timeout(parse_seconds("timeout", timeout)?);
+    }
+    if let Some(timeout) = value.get("connect_timeout") {
+        builder = builder.connect_timeout(parse_seconds("connect_timeout", timeout)?);
+    }
+    if let Some(timeout) = value.get("read_timeout") {
+        builder = builder.read_timeout(parse_seconds("read_timeout", timeout)?);
+    }
+    if let Some(user_agent) = value.get("user_agent") {
+        builder = builder.user_agent(user_agent);
+    }
+    if let Some(proxy) = value.get("proxy") {
+        // http://, https://, socks5://, and socks5h:// are all fine here
+        let proxy = Proxy::all(proxy).map_err(|e| format!("Invalid proxy {}: {}", proxy, e))?;
+        builder = builder.proxy(proxy);
+    }
+    if let Some(ca_bundle) = value.get("ca_bundle") {
+        let certs = Certificate::from_pem_bundle(&read_config_file("ca_bundle", ca_bundle)?)
+            .map_err(|e| format!("Invalid ca_bundle {}: {}", ca_bundle, e))?;
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+    if let Some(client_cert) = value.get("client_cert") {
+        let cert = read_config_file("client_cert", client_cert)?;
+        // the key may live in the same PEM as the certificate
+        let key = match value.get("client_key") {
+            Some(client_key) => read_config_file("client_key", client_key)?,
+            None => cert.clone(),
+        };
+        let identity = Identity::from_pkcs8_pem(&cert, &key)
+            .map_err(|e| format!("Invalid client_cert/client_key: {}", e))?;
+        builder = builder.identity(identity);
+    }
+    if let Some(insecure) = value.get("insecure_skip_verify") {
+        builder =
+            builder.danger_accept_invalid_certs(parse_ini_bool("insecure_skip_verify", insecure)?);
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+/// how many requests may be in flight to one server at once, unless configured otherwise
+static DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 4;
+
+/// how to connect to jenkins. fetches run in the background on copies of it.
+#[derive(Debug, Clone)]
 pub struct JenkinsServer {
     url: String,
-    user: String,
-    password: String,
+    /// user and password/API token; `None` for anonymous read access
+    credentials: Option<(String, Password)>,
+    /// TLS certificates aren't verified at all, so the user should be told about it
+    pub insecure_skip_verify: bool,
+    /// how failed requests are retried
+    retry_policy: RetryPolicy,
+    /// caps how many requests may be in flight to this server at once, shared by every copy of
+    /// it
+    request_limiter: Arc<Semaphore>,
+    /// how the server has been behaving lately
+    pub health: ServerHealth,
+    /// why build timings couldn't be fetched from the JSON API, if they couldn't; they aren't
+    /// asked for again
+    pub timings_unavailable: Option<String>,
 
-    #[serde(skip, default)]
-    client: Option<Client>,
+    client: Client,
+    /// whether responses are fetched live, recorded, or replayed
+    http_mode: HttpMode,
 }
 
 impl Display for JenkinsServer {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!(
-            "{}:{}@{}",
-            self.user,
-            "x".repeat(self.password.len()),
-            self.url
-        ))
+        match &self.credentials {
+            Some((user, password)) => {
+                // a password_command that hasn't run yet has nothing to mask
+                let masked = match password.get() {
+                    Some(password) => "x".repeat(password.expose().len()),
+                    None => "(password_command)".to_string(),
+                };
+                f.write_fmt(format_args!("{}:{}@{}", user, masked, self.url))
+            }
+            None => f.write_str(&self.url),
+        }
     }
 }
 
@@ -171,32 +515,229 @@ impl TryFrom<BTreeMap<String, String>> for JenkinsServer {
 
     fn try_from(value: BTreeMap<String, String>) -> Result<Self, Self::Error> {
         let url = value.get("url").ok_or("Missing URL")?.to_owned();
-        let user = value.get("user").ok_or("Missing user")?.to_owned();
-        let password = value.get("password").ok_or("Missing password")?.to_owned();
+        let credentials = resolve_credentials(&value, &url)?;
+        // `query_plugins_info` is accepted but ignored: JJB queries plugin info on startup, and
+        // nothing here needs it
+        let insecure_skip_verify = value
+            .get("insecure_skip_verify")
+            .map(|v| parse_ini_bool("insecure_skip_verify", v))
+            .transpose()?
+            .unwrap_or(false);
+        let max_concurrent_requests = value
+            .get("max_concurrent_requests")
+            .map(|v| {
+                v.parse::<usize>().ok().filter(|v| *v > 0).ok_or(format!(
+                    "max_concurrent_requests is not a positive number: {}",
+                    v
+                ))
+            })
+            .transpose()?
+            .unwrap_or(DEFAULT_MAX_CONCURRENT_REQUESTS);
         Ok(Self {
+            client: build_client(&value)?,
+            retry_policy: RetryPolicy::from_config(&value)?,
+            request_limiter: Arc::new(Semaphore::new(max_concurrent_requests)),
+            health: ServerHealth::default(),
+            timings_unavailable: None,
             url,
-            user,
-            password,
-            client: None,
+            credentials,
+            insecure_skip_verify,
+            http_mode: HttpMode::default(),
         })
     }
 }
 
+/// Jenkins' CSRF protection token
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Crumb {
+    crumb: String,
+    crumb_request_field: String,
+}
+
 impl JenkinsServer {
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn set_http_mode(&mut self, http_mode: HttpMode) {
+        self.http_mode = http_mode;
+    }
+
+    /// take on what a copy of this server learned while fetching in the background
+    pub fn catch_up(&mut self, copy: &JenkinsServer) {
+        self.health = copy.health.clone();
+        self.timings_unavailable = copy.timings_unavailable.clone();
+    }
+
+    /// the user and password to send, running `password_command` if it's needed
+    async fn basic_auth(&self) -> Result<Option<(&str, &Secret)>, String> {
+        match &self.credentials {
+            Some((user, password)) => Ok(Some((user, password.resolve().await?))),
+            None => Ok(None),
+        }
+    }
+
+    /// make a single attempt at a request with basic auth
+    async fn send(
+        &self,
+        method: Method,
+        url: &Url,
+        headers: &HeaderMap,
+        auth: Option<(&str, &Secret)>,
+    ) -> Result<Response, reqwest::Error> {
+        let mut request = self
+            .client
+            .request(method, url.clone())
+            .headers(headers.clone());
+        if let Some((user, password)) = auth {
+            request = request.basic_auth(user, Some(password.expose()));
+        }
+        request.send().await
+    }
+
     /// make a request to the Jenkins server with basic auth
     pub async fn request_with_auth(
         &mut self,
         relative_url: &str,
     ) -> Result<Response, Box<dyn Error>> {
-        // panic!("{:?}", self.client);
+        self.request_with_headers(relative_url, HeaderMap::new())
+            .await
+    }
+
+    /// make a request to the Jenkins server with basic auth and extra headers, retrying with
+    /// backoff if the server is unreachable or asks us to come back later. every request made
+    /// through here is a GET, so retrying is always safe.
+    pub async fn request_with_headers(
+        &mut self,
+        relative_url: &str,
+        headers: HeaderMap,
+    ) -> Result<Response, Box<dyn Error>> {
         let mut url = Url::parse(&self.url)?;
         url = url.join(relative_url)?;
-        let client = self.client.get_or_insert(reqwest::Client::new());
-        Ok(client
-            .get(url)
-            .basic_auth(self.user.clone(), Some(self.password.clone()))
-            .send()
-            .await?)
+        if let HttpMode::Replay(dir) = &self.http_mode {
+            return record::replay(dir, &url);
+        }
+
+        let _permit = self.request_limiter.clone().acquire_owned().await?;
+        let auth = self.basic_auth().await?;
+        let mut retries = 0;
+        let (outcome, latency) = loop {
+            let started = Instant::now();
+            let outcome = self.send(Method::GET, &url, &headers, auth).await;
+            let latency = started.elapsed();
+            let retry_after = match &outcome {
+                Ok(response) => should_retry(response),
+                // anything else, like a request that couldn't be built, will never work
+                Err(e) if e.is_connect() || e.is_timeout() => Some(None),
+                Err(_) => None,
+            };
+            match retry_after {
+                Some(retry_after) if retries < self.retry_policy.max_retries => {
+                    sleep(self.retry_policy.backoff(retries, retry_after)).await;
+                    retries += 1;
+                }
+                _ => break (outcome, latency),
+            }
+        };
+        self.health.update(&outcome, retries, latency);
+
+        let response = outcome?;
+        if matches!(self.health.state, HealthState::AuthFailed) || should_retry(&response).is_some()
+        {
+            return Err(format!("{} returned HTTP {}", url, response.status()).into());
+        }
+        self.record(&url, response).await
+    }
+
+    /// make a single GET request for something that's only nice to have: it isn't retried,
+    /// and doesn't count towards the server's health
+    pub async fn request_once(&self, relative_url: &str) -> Result<Response, Box<dyn Error>> {
+        let url = Url::parse(&self.url)?.join(relative_url)?;
+        if let HttpMode::Replay(dir) = &self.http_mode {
+            return record::replay(dir, &url);
+        }
+        let _permit = self.request_limiter.clone().acquire_owned().await?;
+        let auth = self.basic_auth().await?;
+        let response = self
+            .send(Method::GET, &url, &HeaderMap::new(), auth)
+            .await?;
+        if !response.status().is_success() {
+            return Err(format!("{} returned HTTP {}", url, response.status()).into());
+        }
+        self.record(&url, response).await
+    }
+
+    /// record the response to `url`, if responses are being recorded
+    async fn record(&self, url: &Url, response: Response) -> Result<Response, Box<dyn Error>> {
+        match &self.http_mode {
+            HttpMode::Record(dir) => {
+                let secrets = match &self.credentials {
+                    Some((user, password)) => {
+                        [Some(user.as_str()), password.get().map(Secret::expose)]
+                            .into_iter()
+                            .flatten()
+                            .collect()
+                    }
+                    None => vec![],
+                };
+                record::record(dir, url, response, &secrets).await
+            }
+            _ => Ok(response),
+        }
+    }
+
+    /// a crumb header for POSTs, if the server hands them out. servers without CSRF protection
+    /// don't, and API tokens don't need one.
+    async fn crumb(&self, auth: Option<(&str, &Secret)>) -> Option<(HeaderName, HeaderValue)> {
+        let url = Url::parse(&self.url)
+            .ok()?
+            .join("crumbIssuer/api/json")
+            .ok()?;
+        let response = self
+            .send(Method::GET, &url, &HeaderMap::new(), auth)
+            .await
+            .ok()?;
+        if !response.status().is_success() {
+            return None;
+        }
+        let crumb: Crumb = serde_json::from_str(&response.text().await.ok()?).ok()?;
+        Some((
+            HeaderName::try_from(crumb.crumb_request_field).ok()?,
+            HeaderValue::try_from(crumb.crumb).ok()?,
+        ))
+    }
+
+    /// start a build of the job at `job`, with its default parameters if it has any. unlike
+    /// every other request, this isn't a GET, so it's never retried, recorded, or replayed.
+    pub async fn trigger_build(&self, job: &Url) -> Result<(), Box<dyn Error>> {
+        if let HttpMode::Replay(_) = self.http_mode {
+            return Err("builds can't be triggered while replaying".into());
+        }
+        let _permit = self.request_limiter.clone().acquire_owned().await?;
+        let auth = self.basic_auth().await?;
+        let mut headers = HeaderMap::new();
+        if let Some((name, value)) = self.crumb(auth).await {
+            headers.insert(name, value);
+        }
+        let mut response = self
+            .send(Method::POST, &job.join("build")?, &headers, auth)
+            .await?;
+        // parameterized jobs can only be built with parameters
+        if response.status() == StatusCode::BAD_REQUEST {
+            response = self
+                .send(
+                    Method::POST,
+                    &job.join("buildWithParameters")?,
+                    &headers,
+                    auth,
+                )
+                .await?;
+        }
+        if !response.status().is_success() {
+            return Err(format!("{} returned HTTP {}", response.url(), response.status()).into());
+        }
+        Ok(())
     }
 }
 
@@ -204,12 +745,10 @@ impl JenkinsServer {
 pub async fn fetch_jenkins_results(
     jenkins_server: &mut JenkinsServer,
 ) -> Result<impl Iterator<Item = JenkinsResult>, Box<dyn Error>> {
-    let xml_resp = jenkins_server
-        .request_with_auth("/rssAll")
-        .await?
-        .text()
-        .await?;
-    let feed = Feed::from_str(&xml_resp).unwrap();
+    let response = jenkins_server.request_with_auth("/rssAll").await?;
+    let xml_resp = response.text().await?;
+    // a proxy's login page or an HTML error page isn't a feed
+    let feed = Feed::from_str(&xml_resp).map_err(|e| format!("Not an Atom feed: {}", e))?;
 
     Ok(feed
         .entries
@@ -217,21 +756,109 @@ pub async fn fetch_jenkins_results(
         .filter_map(|entry| JenkinsResult::try_from(entry).ok()))
 }
 
-/// read the specified JJB config file and turn it into JenkinsServers
-pub fn read_jenkins_config_file<P: AsRef<Path>>(
-    path: P,
-) -> Result<BTreeMap<String, JenkinsServer>, Box<dyn Error>> {
+/// a build, as the JSON API describes it
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiBuild {
+    url: String,
+    /// milliseconds since the epoch
+    timestamp: i64,
+    /// milliseconds
+    duration: u64,
+    /// milliseconds, or -1 if there's nothing to go by
+    estimated_duration: i64,
+    building: bool,
+}
+
+/// a job, or a folder of them, as the JSON API describes it
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ApiJob {
+    builds: Vec<ApiBuild>,
+    jobs: Vec<ApiJob>,
+}
+
+/// recent builds of every job, and of the jobs in folders one level down
+const BUILD_TIMINGS_URL: &str = "api/json?tree=jobs[\
+    builds[url,timestamp,duration,estimatedDuration,building]{0,20},\
+    jobs[builds[url,timestamp,duration,estimatedDuration,building]{0,20}]]";
+
+/// ask the JSON API how long recent builds took, since `rssAll.atom` doesn't say, keyed by their
+/// links. it's only asked once, since not every server allows it.
+pub async fn fetch_build_timings(
+    jenkins_server: &JenkinsServer,
+) -> Result<HashMap<Url, BuildTiming>, Box<dyn Error>> {
+    let response = jenkins_server.request_once(BUILD_TIMINGS_URL).await?;
+    let json_resp = response.text().await?;
+    let root: ApiJob = serde_json::from_str(&json_resp)?;
+    let mut timings = HashMap::new();
+    let mut jobs = root.jobs;
+    while let Some(job) = jobs.pop() {
+        for build in job.builds {
+            let (Ok(url), Some(started)) = (
+                Url::parse(&build.url),
+                DateTime::from_timestamp_millis(build.timestamp),
+            ) else {
+                continue;
+            };
+            let timing = BuildTiming {
+                started,
+                duration: Duration::from_millis(build.duration),
+                estimated: u64::try_from(build.estimated_duration)
+                    .ok()
+                    .map(Duration::from_millis),
+                building: build.building,
+            };
+            timings.insert(url, timing);
+        }
+        jobs.extend(job.jobs);
+    }
+    Ok(timings)
+}
+
+/// the servers found in a JJB config file
+#[derive(Debug, Default)]
+pub struct JenkinsConfig {
+    /// map of section name to server
+    pub servers: BTreeMap<String, JenkinsServer>,
+    /// map of section name to why it couldn't be turned into a server
+    pub skipped: BTreeMap<String, String>,
+}
+
+/// read the specified JJB config file and turn it into JenkinsServers. every server section
+/// inherits keys from `[jenkins]` and overrides whichever it sets itself.
+pub fn read_jenkins_config_file<P: AsRef<Path>>(path: P) -> Result<JenkinsConfig, Box<dyn Error>> {
     let jenkins_file_content = fs::read_to_string(path)?;
-    let config_with_extras: BTreeMap<String, BTreeMap<String, String>> =
+    let mut config_with_extras: BTreeMap<String, BTreeMap<String, String>> =
         serde_ini::from_str(&jenkins_file_content)?;
-    let parsed_jenkins_config: BTreeMap<String, JenkinsServer> = config_with_extras
-        .into_iter()
-        .filter_map(|(k, v)| {
-            if let Ok(server) = JenkinsServer::try_from(v) {
-                return Some((k, server));
+
+    // `[job_builder]` holds JJB's own settings, which have nothing to do with talking to
+    // Jenkins
+    config_with_extras.remove("job_builder");
+    let mut config = JenkinsConfig::default();
+    let defaults = config_with_extras
+        .get(DEFAULT_SECTION)
+        .cloned()
+        .unwrap_or_default();
+
+    for (name, values) in config_with_extras {
+        if name.starts_with(PLUGIN_SECTION_PREFIX) {
+            continue;
+        }
+        // `[jenkins]` may only hold defaults for the other sections
+        if name == DEFAULT_SECTION && !values.contains_key("url") {
+            continue;
+        }
+        let mut merged = defaults.clone();
+        merged.extend(values);
+        match JenkinsServer::try_from(merged) {
+            Ok(server) => {
+                config.servers.insert(name, server);
             }
-            None
-        })
-        .collect();
-    Ok(parsed_jenkins_config)
+            Err(reason) => {
+                config.skipped.insert(name, reason);
+            }
+        }
+    }
+    Ok(config)
 }
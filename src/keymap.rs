@@ -0,0 +1,831 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fmt::Display,
+    fs,
+    path::Path,
+};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+
+/// everything a key can be bound to. which pane is active decides what some of them do, e.g.
+/// `down` selects the next build in the job list, but scrolls the logs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Action {
+    Quit,
+    Help,
+    CommandPalette,
+    FocusServers,
+    FocusJobs,
+    FocusLogs,
+    NextPane,
+    PreviousPane,
+    ToggleMouse,
+    GrowPane,
+    ShrinkPane,
+    ToggleZoom,
+    CycleLayout,
+
+    Down,
+    Up,
+    Left,
+    Right,
+    PageDown,
+    PageUp,
+    Top,
+    Bottom,
+    Refresh,
+
+    ToggleMark,
+    ToggleDiff,
+    CopyLink,
+    CopyJobLink,
+    OpenBuild,
+    OpenJob,
+    ExportCsv,
+    ExportMarkdown,
+    CycleSort,
+    ReverseSort,
+
+    ToggleWrap,
+    LoadFullLogs,
+    NextProblem,
+    PreviousProblem,
+    ToggleProblems,
+    ToggleFold,
+    ToggleAllFolds,
+    TogglePipeline,
+    CycleTimestamps,
+    CopyLines,
+    CopyLog,
+    OpenConsole,
+    SaveLog,
+    SaveStrippedLog,
+    PageLogs,
+    EditLogs,
+    ToggleSelection,
+    ToggleLineNumbers,
+    Search,
+    NextMatch,
+    PreviousMatch,
+    /// back out of a search, selection, or the cursor
+    Back,
+}
+
+/// every action, with its name in the keymap file and what it does
+pub const ACTIONS: &[(Action, &str, &str)] = &[
+    (Action::Quit, "quit", "quit"),
+    (Action::Help, "help", "show the keys for this pane"),
+    (
+        Action::CommandPalette,
+        "command-palette",
+        "run a command by name",
+    ),
+    (
+        Action::FocusServers,
+        "focus-servers",
+        "go to the server list",
+    ),
+    (Action::FocusJobs, "focus-jobs", "go to the job list"),
+    (Action::FocusLogs, "focus-logs", "go to the logs"),
+    (Action::NextPane, "next-pane", "go to the next pane"),
+    (
+        Action::PreviousPane,
+        "previous-pane",
+        "go to the previous pane",
+    ),
+    (
+        Action::ToggleMouse,
+        "toggle-mouse",
+        "release or capture the mouse",
+    ),
+    (Action::GrowPane, "grow-pane", "make this pane bigger"),
+    (Action::ShrinkPane, "shrink-pane", "make this pane smaller"),
+    (
+        Action::ToggleZoom,
+        "toggle-zoom",
+        "show just this pane, or all of them",
+    ),
+    (
+        Action::CycleLayout,
+        "cycle-layout",
+        "switch to the next layout",
+    ),
+    (Action::Down, "down", "select the next one, or scroll down"),
+    (Action::Up, "up", "select the previous one, or scroll up"),
+    (Action::Left, "left", "scroll left"),
+    (Action::Right, "right", "scroll right"),
+    (Action::PageDown, "page-down", "scroll down a page"),
+    (Action::PageUp, "page-up", "scroll up a page"),
+    (Action::Top, "top", "go to the top"),
+    (Action::Bottom, "bottom", "go to the bottom"),
+    (Action::Refresh, "refresh", "refresh this pane"),
+    (
+        Action::ToggleMark,
+        "toggle-mark",
+        "mark the build to diff against",
+    ),
+    (
+        Action::ToggleDiff,
+        "toggle-diff",
+        "diff the marked build against this one",
+    ),
+    (Action::CopyLink, "copy-link", "copy the build's URL"),
+    (Action::CopyJobLink, "copy-job-link", "copy the job's URL"),
+    (
+        Action::OpenBuild,
+        "open-build",
+        "open the build in the browser",
+    ),
+    (Action::OpenJob, "open-job", "open the job in the browser"),
+    (Action::ExportCsv, "export-csv", "export the jobs as CSV"),
+    (
+        Action::ExportMarkdown,
+        "export-markdown",
+        "export the jobs as Markdown",
+    ),
+    (
+        Action::CycleSort,
+        "cycle-sort",
+        "sort the jobs by the next column",
+    ),
+    (
+        Action::ReverseSort,
+        "reverse-sort",
+        "reverse the jobs' order",
+    ),
+    (Action::ToggleWrap, "toggle-wrap", "wrap long lines"),
+    (
+        Action::LoadFullLogs,
+        "load-full-logs",
+        "fetch all of a big log",
+    ),
+    (
+        Action::NextProblem,
+        "next-problem",
+        "go to the next problem (or change)",
+    ),
+    (
+        Action::PreviousProblem,
+        "previous-problem",
+        "go to the previous problem (or change)",
+    ),
+    (
+        Action::ToggleProblems,
+        "toggle-problems",
+        "list the problems under the logs",
+    ),
+    (
+        Action::ToggleFold,
+        "toggle-fold",
+        "collapse or expand this pipeline block",
+    ),
+    (
+        Action::ToggleAllFolds,
+        "toggle-all-folds",
+        "collapse or expand every pipeline block",
+    ),
+    (
+        Action::TogglePipeline,
+        "toggle-pipeline",
+        "hide or show [Pipeline] lines",
+    ),
+    (
+        Action::CycleTimestamps,
+        "cycle-timestamps",
+        "show, strip, or relativize timestamps",
+    ),
+    (
+        Action::CopyLines,
+        "copy-lines",
+        "copy the selected lines, or the ones on screen",
+    ),
+    (Action::CopyLog, "copy-log", "copy the whole log"),
+    (
+        Action::OpenConsole,
+        "open-console",
+        "open the console output in the browser",
+    ),
+    (
+        Action::SaveLog,
+        "save-log",
+        "save the log (or the selected lines)",
+    ),
+    (
+        Action::SaveStrippedLog,
+        "save-stripped-log",
+        "save the log without colors",
+    ),
+    (Action::PageLogs, "page-logs", "show the log in $PAGER"),
+    (Action::EditLogs, "edit-logs", "open the log in $EDITOR"),
+    (
+        Action::ToggleSelection,
+        "toggle-selection",
+        "start or stop selecting lines",
+    ),
+    (
+        Action::ToggleLineNumbers,
+        "toggle-line-numbers",
+        "show or hide line numbers",
+    ),
+    (
+        Action::Search,
+        "search",
+        "search the log (or the selected lines)",
+    ),
+    (Action::NextMatch, "next-match", "go to the next match"),
+    (
+        Action::PreviousMatch,
+        "previous-match",
+        "go to the previous match",
+    ),
+    (
+        Action::Back,
+        "back",
+        "clear the search, selection, or cursor",
+    ),
+];
+
+impl Action {
+    fn entry(self) -> &'static (Action, &'static str, &'static str) {
+        ACTIONS
+            .iter()
+            .find(|(action, _, _)| *action == self)
+            .expect("every action is listed")
+    }
+
+    /// what it's called in the keymap file
+    pub fn name(self) -> &'static str {
+        self.entry().1
+    }
+
+    pub fn description(self) -> &'static str {
+        self.entry().2
+    }
+}
+
+impl TryFrom<String> for Action {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        ACTIONS
+            .iter()
+            .find(|(_, action_name, _)| *action_name == name)
+            .map(|(action, _, _)| *action)
+            .ok_or_else(|| format!("unknown action {:?}", name))
+    }
+}
+
+/// where a binding applies: everywhere, or only while a pane is active
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Global,
+    Servers,
+    Jobs,
+    Logs,
+}
+
+impl Scope {
+    /// the scope of the active pane
+    pub fn of_pane(pane: i8) -> Self {
+        match pane {
+            1 => Scope::Servers,
+            2 => Scope::Jobs,
+            _ => Scope::Logs,
+        }
+    }
+}
+
+/// a key along with the modifiers held down with it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        // shift is already part of characters (`G`, `#`) and back tab, so it's dropped to match
+        // however the terminal reports them
+        let mut modifiers =
+            modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        let shift = modifiers.contains(KeyModifiers::SHIFT);
+        let code = match code {
+            // terminals can't tell ctrl-c from ctrl-C
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::CONTROL) => {
+                KeyCode::Char(c.to_ascii_lowercase())
+            }
+            KeyCode::Char(c) if shift => KeyCode::Char(c.to_ascii_uppercase()),
+            KeyCode::Tab if shift => KeyCode::BackTab,
+            code => code,
+        };
+        if matches!(code, KeyCode::Char(_) | KeyCode::BackTab) {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        Self { code, modifiers }
+    }
+}
+
+impl From<KeyEvent> for KeyChord {
+    fn from(key_event: KeyEvent) -> Self {
+        Self::new(key_event.code, key_event.modifiers)
+    }
+}
+
+const NAMED_KEYS: [(&str, KeyCode); 15] = [
+    ("esc", KeyCode::Esc),
+    ("enter", KeyCode::Enter),
+    ("tab", KeyCode::Tab),
+    ("backtab", KeyCode::BackTab),
+    ("backspace", KeyCode::Backspace),
+    ("delete", KeyCode::Delete),
+    ("insert", KeyCode::Insert),
+    ("home", KeyCode::Home),
+    ("end", KeyCode::End),
+    ("pageup", KeyCode::PageUp),
+    ("pagedown", KeyCode::PageDown),
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+];
+
+const MODIFIERS: [(&str, KeyModifiers); 4] = [
+    ("ctrl-", KeyModifiers::CONTROL),
+    ("alt-", KeyModifiers::ALT),
+    ("meta-", KeyModifiers::ALT),
+    ("shift-", KeyModifiers::SHIFT),
+];
+
+/// a named key (`esc`, `pagedown`, `f5`, `space`), or a single character
+fn parse_key(key: &str) -> Option<KeyCode> {
+    let lower = key.to_ascii_lowercase();
+    if let Some((_, code)) = NAMED_KEYS.iter().find(|(name, _)| *name == lower) {
+        return Some(*code);
+    }
+    if lower == "space" {
+        return Some(KeyCode::Char(' '));
+    }
+    if let Some(n) = lower.strip_prefix('f').and_then(|n| n.parse().ok()) {
+        return Some(KeyCode::F(n));
+    }
+    let mut chars = key.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(KeyCode::Char(c)),
+        _ => None,
+    }
+}
+
+/// parse a sequence of keys: chords separated by spaces (`ctrl-x ctrl-s`, `g g`). a word that
+/// isn't a key name or a single character is an error, so a typo like `pgdn` isn't taken for
+/// four keys.
+fn parse_keys(keys: &str) -> Result<Vec<KeyChord>, String> {
+    let mut sequence = vec![];
+    for word in keys.split_whitespace() {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = word;
+        // `-` on its own, or after a modifier, is the minus key
+        while let Some((prefix, modifier)) = MODIFIERS.iter().find(|(prefix, _)| {
+            rest.len() > prefix.len() && rest.to_ascii_lowercase().starts_with(prefix)
+        }) {
+            modifiers |= *modifier;
+            rest = &rest[prefix.len()..];
+        }
+        match parse_key(rest) {
+            Some(code) => sequence.push(KeyChord::new(code, modifiers)),
+            None => {
+                return Err(format!(
+                    "unknown key {:?} in {:?} (keys in a sequence are separated by spaces, \
+                     like \"g g\")",
+                    rest, keys
+                ))
+            }
+        }
+    }
+    if sequence.is_empty() {
+        return Err("empty key binding".to_string());
+    }
+    Ok(sequence)
+}
+
+impl Display for KeyChord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (prefix, modifier) in [MODIFIERS[0], MODIFIERS[1], MODIFIERS[3]] {
+            if self.modifiers.contains(modifier) {
+                f.write_str(prefix)?;
+            }
+        }
+        match self.code {
+            KeyCode::Char(' ') => f.write_str("space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(n) => write!(f, "f{}", n),
+            code => match NAMED_KEYS.iter().find(|(_, named)| *named == code) {
+                Some((name, _)) => f.write_str(name),
+                None => write!(f, "{:?}", code),
+            },
+        }
+    }
+}
+
+/// a key sequence the way it'd be written in the keymap file
+fn keys_to_string(keys: &[KeyChord]) -> String {
+    let keys: Vec<String> = keys.iter().map(KeyChord::to_string).collect();
+    keys.join(" ")
+}
+
+/// which key sequences do what, in one scope
+type Bindings = HashMap<Vec<KeyChord>, Action>;
+
+/// one key sequence or several, as written in the keymap file
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Keys {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// the keymap file: for each scope, actions and the keys that do them. an action listed here
+/// loses its default keys in that scope, so `down = []` unbinds it.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct KeymapFile {
+    global: HashMap<Action, Keys>,
+    servers: HashMap<Action, Keys>,
+    jobs: HashMap<Action, Keys>,
+    logs: HashMap<Action, Keys>,
+}
+
+const DEFAULT_GLOBAL: &[(Action, &[&str])] = &[
+    (Action::Quit, &["q", "esc", "ctrl-c"]),
+    (Action::Help, &["?"]),
+    (Action::CommandPalette, &[":"]),
+    (Action::FocusServers, &["1"]),
+    (Action::FocusJobs, &["2"]),
+    (Action::FocusLogs, &["3"]),
+    (Action::NextPane, &["n"]),
+    (Action::PreviousPane, &["p"]),
+    (Action::ToggleMouse, &["M"]),
+    (Action::GrowPane, &["+", "="]),
+    (Action::ShrinkPane, &["-"]),
+    (Action::ToggleZoom, &["f"]),
+    (Action::CycleLayout, &["F"]),
+];
+
+const DEFAULT_SERVERS: &[(Action, &[&str])] = &[
+    (Action::Down, &["j", "down"]),
+    (Action::Up, &["k", "up"]),
+    (Action::Top, &["g g", "home"]),
+    (Action::Bottom, &["G", "end"]),
+    (Action::Refresh, &["r"]),
+];
+
+const DEFAULT_JOBS: &[(Action, &[&str])] = &[
+    (Action::Down, &["j", "down"]),
+    (Action::Up, &["k", "up"]),
+    (Action::Top, &["g g", "home"]),
+    (Action::Bottom, &["G", "end"]),
+    (Action::Refresh, &["r"]),
+    (Action::ToggleMark, &["m"]),
+    (Action::ToggleDiff, &["d"]),
+    (Action::CopyLink, &["y"]),
+    (Action::CopyJobLink, &["Y"]),
+    (Action::OpenBuild, &["O"]),
+    (Action::OpenJob, &["J"]),
+    (Action::ExportCsv, &["x"]),
+    (Action::ExportMarkdown, &["X"]),
+    (Action::CycleSort, &["s"]),
+    (Action::ReverseSort, &["S"]),
+];
+
+const DEFAULT_LOGS: &[(Action, &[&str])] = &[
+    (Action::Down, &["j", "down"]),
+    (Action::Up, &["k", "up"]),
+    (Action::Left, &["h", "left"]),
+    (Action::Right, &["l", "right"]),
+    (Action::PageDown, &["pagedown"]),
+    (Action::PageUp, &["pageup"]),
+    (Action::Top, &["g g", "home"]),
+    (Action::Bottom, &["G", "end"]),
+    (Action::ToggleWrap, &["w"]),
+    (Action::LoadFullLogs, &["L"]),
+    (Action::NextProblem, &["e"]),
+    (Action::PreviousProblem, &["E"]),
+    (Action::ToggleProblems, &["P"]),
+    (Action::ToggleDiff, &["d"]),
+    (Action::ToggleFold, &["z"]),
+    (Action::ToggleAllFolds, &["Z"]),
+    (Action::TogglePipeline, &["b"]),
+    (Action::CycleTimestamps, &["t"]),
+    (Action::CopyLines, &["y"]),
+    (Action::CopyLog, &["Y"]),
+    (Action::OpenConsole, &["O"]),
+    (Action::SaveLog, &["s"]),
+    (Action::SaveStrippedLog, &["S"]),
+    (Action::PageLogs, &["|"]),
+    (Action::EditLogs, &["o"]),
+    (Action::ToggleSelection, &["v"]),
+    (Action::ToggleLineNumbers, &["#"]),
+    (Action::Search, &["/"]),
+    (Action::NextMatch, &["n"]),
+    (Action::PreviousMatch, &["N"]),
+    (Action::Back, &["esc"]),
+];
+
+/// what a key sequence that's been typed so far does
+#[derive(Debug, PartialEq, Eq)]
+pub enum Lookup {
+    /// the actions it's bound to, most specific scope first
+    Found(Vec<Action>),
+    /// nothing yet, but it's the start of a longer binding
+    Pending,
+    NotFound,
+}
+
+/// key bindings for every scope
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    global: Bindings,
+    servers: Bindings,
+    jobs: Bindings,
+    logs: Bindings,
+}
+
+/// bind `keys` to `action`, unless they already do something else
+fn bind(bindings: &mut Bindings, keys: &str, action: Action, scope: &str) -> Result<(), String> {
+    let keys = parse_keys(keys)?;
+    match bindings.get(&keys) {
+        Some(bound) if *bound != action => Err(format!(
+            "{:?} is bound to both {} and {} in [{}]",
+            keys_to_string(&keys),
+            bound.name(),
+            action.name(),
+            scope
+        )),
+        _ => {
+            bindings.insert(keys, action);
+            Ok(())
+        }
+    }
+}
+
+/// the default bindings, minus the actions in `overrides`, plus `overrides`, for the scope
+/// called `scope`
+fn bindings(
+    scope: &str,
+    defaults: &[(Action, &[&str])],
+    overrides: HashMap<Action, Keys>,
+) -> Result<Bindings, String> {
+    let mut bindings = Bindings::new();
+    for (action, keys) in defaults {
+        if !overrides.contains_key(action) {
+            for keys in *keys {
+                bind(&mut bindings, keys, *action, scope)?;
+            }
+        }
+    }
+    for (action, keys) in overrides {
+        let keys = match keys {
+            Keys::One(keys) => vec![keys],
+            Keys::Many(keys) => keys,
+        };
+        for keys in keys {
+            bind(&mut bindings, &keys, action, scope)?;
+        }
+    }
+    check_prefixes(&bindings, &bindings, scope)?;
+    Ok(bindings)
+}
+
+/// make sure no binding in `bindings` starts another in `others` (or the other way round),
+/// since the longer one could never be typed. they're both in effect in `scope`.
+fn check_prefixes(bindings: &Bindings, others: &Bindings, scope: &str) -> Result<(), String> {
+    for (keys, action) in bindings {
+        for (other_keys, other_action) in others {
+            let (short, long) = if keys.len() < other_keys.len() {
+                ((keys, action), (other_keys, other_action))
+            } else {
+                ((other_keys, other_action), (keys, action))
+            };
+            if short.0.len() < long.0.len() && long.0.starts_with(short.0) {
+                return Err(format!(
+                    "{:?} ({}) can never be typed, since {:?} ({}) starts it, in [{}]",
+                    keys_to_string(long.0),
+                    long.1.name(),
+                    keys_to_string(short.0),
+                    short.1.name(),
+                    scope
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::from_file(KeymapFile::default()).expect("the default keymap is valid")
+    }
+}
+
+impl Keymap {
+    fn from_file(file: KeymapFile) -> Result<Self, String> {
+        let keymap = Self {
+            global: bindings("global", DEFAULT_GLOBAL, file.global)?,
+            servers: bindings("servers", DEFAULT_SERVERS, file.servers)?,
+            jobs: bindings("jobs", DEFAULT_JOBS, file.jobs)?,
+            logs: bindings("logs", DEFAULT_LOGS, file.logs)?,
+        };
+        // a pane's keys are looked up along with the global ones, so they mustn't get in each
+        // other's way either (though a pane may rebind a global key outright)
+        for (pane, name) in [
+            (&keymap.servers, "servers"),
+            (&keymap.jobs, "jobs"),
+            (&keymap.logs, "logs"),
+        ] {
+            check_prefixes(pane, &keymap.global, name)?;
+        }
+        Ok(keymap)
+    }
+
+    /// read the keymap file, as TOML or YAML if the extension says so
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let content = fs::read_to_string(path)?;
+        let file: KeymapFile = match path.extension().and_then(|e| e.to_str()) {
+            Some("yaml" | "yml") => serde_yaml::from_str(&content)?,
+            _ => toml::from_str(&content)?,
+        };
+        Ok(Self::from_file(file)?)
+    }
+
+    fn scope(&self, scope: Scope) -> &Bindings {
+        match scope {
+            Scope::Global => &self.global,
+            Scope::Servers => &self.servers,
+            Scope::Jobs => &self.jobs,
+            Scope::Logs => &self.logs,
+        }
+    }
+
+    /// look up what `keys` does in the active pane's scope, and then globally
+    pub fn lookup(&self, pane: Scope, keys: &[KeyChord]) -> Lookup {
+        let scopes = [self.scope(pane), self.scope(Scope::Global)];
+        let found: Vec<Action> = scopes
+            .iter()
+            .filter_map(|bindings| bindings.get(keys).copied())
+            .collect();
+        if !found.is_empty() {
+            Lookup::Found(found)
+        } else if scopes
+            .iter()
+            .flat_map(|bindings| bindings.keys())
+            .any(|bound| bound.len() > keys.len() && bound.starts_with(keys))
+        {
+            Lookup::Pending
+        } else {
+            Lookup::NotFound
+        }
+    }
+
+    /// every action bound in `scope`, in order, with the keys that do it
+    pub fn bindings(&self, scope: Scope) -> BTreeMap<Action, Vec<String>> {
+        let mut bindings: BTreeMap<Action, Vec<String>> = BTreeMap::new();
+        for (keys, action) in self.scope(scope) {
+            bindings
+                .entry(*action)
+                .or_default()
+                .push(keys_to_string(keys));
+        }
+        for keys in bindings.values_mut() {
+            // shortest first, so single keys come before sequences and names
+            keys.sort_by_key(|keys| (keys.len(), keys.clone()));
+        }
+        bindings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chord(code: KeyCode, modifiers: KeyModifiers) -> KeyChord {
+        KeyChord::new(code, modifiers)
+    }
+
+    fn file(toml: &str) -> KeymapFile {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn parses_keys() {
+        assert_eq!(
+            parse_keys("G").unwrap(),
+            [chord(KeyCode::Char('G'), KeyModifiers::NONE)]
+        );
+        assert_eq!(
+            parse_keys("ctrl-x ctrl-C").unwrap(),
+            [
+                chord(KeyCode::Char('x'), KeyModifiers::CONTROL),
+                chord(KeyCode::Char('c'), KeyModifiers::CONTROL),
+            ]
+        );
+        assert_eq!(
+            parse_keys("g g").unwrap(),
+            [
+                chord(KeyCode::Char('g'), KeyModifiers::NONE),
+                chord(KeyCode::Char('g'), KeyModifiers::NONE),
+            ]
+        );
+        assert_eq!(
+            parse_keys("alt-PageDown f5 space -").unwrap(),
+            [
+                chord(KeyCode::PageDown, KeyModifiers::ALT),
+                chord(KeyCode::F(5), KeyModifiers::NONE),
+                chord(KeyCode::Char(' '), KeyModifiers::NONE),
+                chord(KeyCode::Char('-'), KeyModifiers::NONE),
+            ]
+        );
+        assert_eq!(
+            parse_keys("ctrl--").unwrap(),
+            [chord(KeyCode::Char('-'), KeyModifiers::CONTROL)]
+        );
+        assert_eq!(
+            parse_keys("shift-tab").unwrap(),
+            [chord(KeyCode::BackTab, KeyModifiers::NONE)]
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_key_names() {
+        assert!(parse_keys("pgdn").is_err());
+        assert!(parse_keys("gg").is_err());
+        assert!(parse_keys("ctrl-foo").is_err());
+        assert!(parse_keys("  ").is_err());
+    }
+
+    #[test]
+    fn keys_round_trip() {
+        for keys in ["g g", "ctrl-x ctrl-c", "alt-pagedown", "space", "f12", "#"] {
+            assert_eq!(keys_to_string(&parse_keys(keys).unwrap()), keys);
+        }
+    }
+
+    #[test]
+    fn default_keymap_is_valid() {
+        let keymap = Keymap::from_file(KeymapFile::default()).unwrap();
+        let gg = parse_keys("g g").unwrap();
+        assert_eq!(
+            keymap.lookup(Scope::Logs, &gg),
+            Lookup::Found(vec![Action::Top])
+        );
+        assert_eq!(keymap.lookup(Scope::Logs, &gg[..1]), Lookup::Pending);
+    }
+
+    #[test]
+    fn overrides_replace_defaults() {
+        let keymap = Keymap::from_file(file("[logs]\ndown = \"ctrl-n\"\n")).unwrap();
+        let ctrl_n = parse_keys("ctrl-n").unwrap();
+        let j = parse_keys("j").unwrap();
+        assert_eq!(
+            keymap.lookup(Scope::Logs, &ctrl_n),
+            Lookup::Found(vec![Action::Down])
+        );
+        assert_eq!(keymap.lookup(Scope::Logs, &j), Lookup::NotFound);
+    }
+
+    #[test]
+    fn rejects_duplicate_bindings() {
+        // `w` is already toggle-wrap in the logs
+        let err = Keymap::from_file(file("[logs]\ndown = \"w\"\n")).unwrap_err();
+        assert!(
+            err.contains("toggle-wrap") && err.contains("[logs]"),
+            "{}",
+            err
+        );
+        let err =
+            Keymap::from_file(file("[jobs]\ndown = \"ctrl-x\"\nup = \"ctrl-x\"\n")).unwrap_err();
+        assert!(err.contains("[jobs]"), "{}", err);
+        // the same action twice is harmless
+        assert!(Keymap::from_file(file("[jobs]\ndown = [\"ctrl-x\", \"ctrl-x\"]\n")).is_ok());
+    }
+
+    #[test]
+    fn a_pane_may_rebind_a_global_key() {
+        let keymap = Keymap::from_file(file("[logs]\ndown = \"q\"\n")).unwrap();
+        let q = parse_keys("q").unwrap();
+        assert_eq!(
+            keymap.lookup(Scope::Logs, &q),
+            Lookup::Found(vec![Action::Down, Action::Quit])
+        );
+    }
+
+    #[test]
+    fn rejects_prefix_conflicts() {
+        let err = Keymap::from_file(file("[logs]\ndown = \"g\"\n")).unwrap_err();
+        assert!(err.contains("can never be typed"), "{}", err);
+        // a global key can shadow a pane's longer binding too
+        let err = Keymap::from_file(file("[global]\nhelp = \"g\"\n")).unwrap_err();
+        assert!(err.contains("can never be typed"), "{}", err);
+    }
+}
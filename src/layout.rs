@@ -0,0 +1,218 @@
+use std::fmt::Display;
+
+use ratatui::layout::{Constraint, Layout, Rect};
+use serde::{Deserialize, Serialize};
+
+/// panes can't be made smaller than this percentage of the screen
+pub const MIN_PANE_PERCENT: u16 = 10;
+
+/// where each pane was drawn as of the last render, to work out what the mouse is over. hidden
+/// panes are empty.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PaneAreas {
+    /// everything but the status bar
+    pub main: Rect,
+    pub server_list: Rect,
+    pub job_list: Rect,
+    pub job_logs: Rect,
+}
+
+/// a border between panes that can be dragged to resize them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaneBorder {
+    /// between the server list and the job list
+    ServerList,
+    /// between the job list and the logs
+    JobList,
+}
+
+/// a built-in arrangement of the panes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LayoutPreset {
+    /// the server list down the left, and the job list over the logs to its right
+    #[default]
+    Default,
+    /// the server list and job list side by side, over logs as wide as the screen
+    WideLog,
+    /// each pane over the next, for narrow terminals
+    Stacked,
+}
+
+impl LayoutPreset {
+    pub fn next(self) -> Self {
+        match self {
+            LayoutPreset::Default => LayoutPreset::WideLog,
+            LayoutPreset::WideLog => LayoutPreset::Stacked,
+            LayoutPreset::Stacked => LayoutPreset::Default,
+        }
+    }
+}
+
+impl Display for LayoutPreset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            LayoutPreset::Default => "default",
+            LayoutPreset::WideLog => "wide log",
+            LayoutPreset::Stacked => "stacked",
+        })
+    }
+}
+
+/// how the panes are laid out: the preset, and how big the panes in it are
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PaneLayout {
+    pub preset: LayoutPreset,
+    /// size of the server list, as a percentage of the screen's width (or height, stacked)
+    pub server_list_percent: u16,
+    /// size of the job list, as a percentage of the screen's height. in the wide log layout,
+    /// the server list is as tall as it.
+    pub job_list_percent: u16,
+}
+
+impl Default for PaneLayout {
+    fn default() -> Self {
+        Self {
+            preset: LayoutPreset::Default,
+            server_list_percent: 33,
+            job_list_percent: 20,
+        }
+    }
+}
+
+/// whether `(column, row)` is on the border between `left` and `right`. both panes' borders
+/// count, since they're drawn side by side.
+fn on_vertical_border(left: Rect, right: Rect, column: u16, row: u16) -> bool {
+    (column + 1 == left.right() || column == right.x) && (left.top()..left.bottom()).contains(&row)
+}
+
+/// whether `(column, row)` is on the border between `top` and `bottom`
+fn on_horizontal_border(top: Rect, bottom: Rect, column: u16, row: u16) -> bool {
+    (row + 1 == top.bottom() || row == bottom.y) && (top.left()..top.right()).contains(&column)
+}
+
+impl PaneLayout {
+    /// where each pane goes in `main`, without the server list unless `show_servers`. a
+    /// `zoomed` pane gets all of it, and the others nothing.
+    pub fn areas(&self, main: Rect, show_servers: bool, zoomed: Option<i8>) -> PaneAreas {
+        if let Some(pane) = zoomed {
+            let zoomed_to = |p: i8| if p == pane { main } else { Rect::default() };
+            return PaneAreas {
+                main,
+                server_list: zoomed_to(1),
+                job_list: zoomed_to(2),
+                job_logs: zoomed_to(3),
+            };
+        }
+        let servers = if show_servers {
+            Constraint::Percentage(self.server_list_percent)
+        } else {
+            Constraint::Length(0)
+        };
+        let jobs = Constraint::Percentage(self.job_list_percent);
+        let (server_list, job_list, job_logs) = match self.preset {
+            LayoutPreset::Default => {
+                let [server_list, job_pane] =
+                    Layout::horizontal([servers, Constraint::Fill(1)]).areas(main);
+                let [job_list, job_logs] =
+                    Layout::vertical([jobs, Constraint::Fill(1)]).areas(job_pane);
+                (server_list, job_list, job_logs)
+            }
+            LayoutPreset::WideLog => {
+                let [lists, job_logs] = Layout::vertical([jobs, Constraint::Fill(1)]).areas(main);
+                let [server_list, job_list] =
+                    Layout::horizontal([servers, Constraint::Fill(1)]).areas(lists);
+                (server_list, job_list, job_logs)
+            }
+            LayoutPreset::Stacked => {
+                let [server_list, job_list, job_logs] =
+                    Layout::vertical([servers, jobs, Constraint::Fill(1)]).areas(main);
+                (server_list, job_list, job_logs)
+            }
+        };
+        PaneAreas {
+            main,
+            server_list,
+            job_list,
+            job_logs,
+        }
+    }
+
+    /// the border between panes at a point on screen, if there is one
+    pub fn border_at(&self, areas: &PaneAreas, column: u16, row: u16) -> Option<PaneBorder> {
+        let PaneAreas {
+            server_list,
+            job_list,
+            job_logs,
+            ..
+        } = *areas;
+        let on_server_border = !server_list.is_empty()
+            && match self.preset {
+                LayoutPreset::Default | LayoutPreset::WideLog => {
+                    on_vertical_border(server_list, job_list, column, row)
+                }
+                LayoutPreset::Stacked => on_horizontal_border(server_list, job_list, column, row),
+            };
+        let lists = match self.preset {
+            LayoutPreset::WideLog => job_list.union(server_list),
+            LayoutPreset::Default | LayoutPreset::Stacked => job_list,
+        };
+        if on_server_border {
+            Some(PaneBorder::ServerList)
+        } else if !job_logs.is_empty() && on_horizontal_border(lists, job_logs, column, row) {
+            Some(PaneBorder::JobList)
+        } else {
+            None
+        }
+    }
+
+    /// move `border` to a point on screen
+    pub fn drag_border(&mut self, areas: &PaneAreas, border: PaneBorder, column: u16, row: u16) {
+        let main = areas.main;
+        let percent =
+            |offset: u16, length: u16| (offset as u32 * 100 / length.max(1) as u32) as u16;
+        match (border, self.preset) {
+            (PaneBorder::ServerList, LayoutPreset::Default | LayoutPreset::WideLog) => {
+                self.server_list_percent = percent(column.saturating_sub(main.x), main.width)
+            }
+            (PaneBorder::ServerList, LayoutPreset::Stacked) => {
+                self.server_list_percent = percent(row.saturating_sub(main.y), main.height)
+            }
+            // the job list starts under the server list when they're stacked
+            (PaneBorder::JobList, _) => {
+                self.job_list_percent = percent(row.saturating_sub(areas.job_list.y), main.height)
+            }
+        }
+        self.clamp();
+    }
+
+    /// make `pane` `by` percent of the screen bigger, or smaller if it's negative. the logs
+    /// grow by shrinking the job list.
+    pub fn resize(&mut self, pane: i8, by: i16) {
+        let grow = |percent: u16, by: i16| percent.saturating_add_signed(by);
+        match pane {
+            1 => self.server_list_percent = grow(self.server_list_percent, by),
+            2 => self.job_list_percent = grow(self.job_list_percent, by),
+            _ => self.job_list_percent = grow(self.job_list_percent, -by),
+        }
+        self.clamp();
+    }
+
+    /// keep every pane at least `MIN_PANE_PERCENT` of the screen
+    pub fn clamp(&mut self) {
+        let max = 100 - MIN_PANE_PERCENT;
+        // stacked, the job list and logs share what the server list leaves
+        let stacked = self.preset == LayoutPreset::Stacked;
+        let max_servers = if stacked { max - MIN_PANE_PERCENT } else { max };
+        self.server_list_percent = self
+            .server_list_percent
+            .clamp(MIN_PANE_PERCENT, max_servers);
+        let max_jobs = if stacked {
+            max - self.server_list_percent
+        } else {
+            max
+        };
+        self.job_list_percent = self.job_list_percent.clamp(MIN_PANE_PERCENT, max_jobs);
+    }
+}
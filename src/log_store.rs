@@ -0,0 +1,107 @@
+use std::{collections::VecDeque, fs, io, path::PathBuf};
+
+use reqwest::Url;
+use serde::Deserialize;
+use tempfile::TempDir;
+
+use crate::{
+    jenkins::{BuildState, JenkinsResult},
+    log_view::Log,
+    record::url_file_name,
+};
+
+/// what to do with logs over the size threshold
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LargeLogMode {
+    /// fetch all of it anyway
+    Full,
+    /// only fetch the start of the log
+    Head,
+    /// only keep the end of the log, which is usually where the failure is
+    #[default]
+    Tail,
+}
+
+/// how much of a log to fetch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogLimit {
+    Full,
+    /// only the first this many bytes
+    Head(u64),
+    /// only the last this many bytes
+    Tail(u64),
+}
+
+/// the name of the file a build's log is spilled to
+fn spill_name(link: &Url) -> String {
+    format!("{}.log", url_file_name(link))
+}
+
+/// keeps track of which logs are held in memory, dropping the least recently viewed ones (and
+/// spilling big ones to temp files, so they don't need fetching again) once there are too many
+#[derive(Debug, Default)]
+pub struct LogStore {
+    /// links of the builds whose logs are in memory, least recently viewed first
+    recent: VecDeque<Url>,
+    /// where logs evicted from memory are spilled, once anything has been. it's private to
+    /// this user, since logs can hold secrets, and removed on drop.
+    spill_dir: Option<TempDir>,
+}
+
+impl LogStore {
+    fn spill_path(&self, link: &Url) -> Option<PathBuf> {
+        Some(self.spill_dir.as_ref()?.path().join(spill_name(link)))
+    }
+
+    /// note that the build's log was just viewed. returns the links of the builds whose logs
+    /// should be dropped from memory to keep at most `max_in_memory` around, which never
+    /// includes `pinned`.
+    pub fn touch(&mut self, link: &Url, max_in_memory: usize, pinned: Option<&Url>) -> Vec<Url> {
+        self.recent.retain(|recent| recent != link);
+        self.recent.push_back(link.clone());
+        let excess = self.recent.len().saturating_sub(max_in_memory.max(1));
+        let mut evicted: Vec<Url> = self.recent.drain(..excess).collect();
+        if let Some(idx) = evicted.iter().position(|evicted| Some(evicted) == pinned) {
+            self.recent.push_front(evicted.remove(idx));
+        }
+        evicted
+    }
+
+    /// drop the build's log from memory, first writing it to a temp file if it's at least
+    /// `spill_threshold` bytes. partial logs aren't spilled, since they'd need re-fetching in
+    /// full anyway, and neither are logs missing the end of a build that's finished since.
+    pub fn evict(&mut self, job: &mut JenkinsResult, spill_threshold: u64) -> io::Result<()> {
+        let logs = std::mem::take(&mut job.logs);
+        if logs.is_truncated()
+            || job.logs_unfinished
+            || (logs.text().len() as u64) < spill_threshold
+        {
+            return Ok(());
+        }
+        let dir = match self.spill_dir.take() {
+            Some(dir) => dir,
+            None => tempfile::Builder::new()
+                .prefix("jenkins-atom-tui-")
+                .tempdir()?,
+        };
+        let path = self
+            .spill_dir
+            .insert(dir)
+            .path()
+            .join(spill_name(&job.link));
+        fs::write(path, logs.text())
+    }
+
+    /// read back a log that was spilled. logs of running builds are still picked up from where
+    /// they left off by the next refresh.
+    pub fn unspill(&self, job: &JenkinsResult) -> Option<Log> {
+        let path = self.spill_path(&job.link)?;
+        let logs = fs::read_to_string(&path).ok()?;
+        // finished builds never change, so keep their file around for the next eviction
+        if job.build_state == BuildState::Unknown {
+            let _ = fs::remove_file(&path);
+        }
+        Some(logs.into())
+    }
+}
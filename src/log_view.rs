@@ -0,0 +1,875 @@
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    fmt::Debug,
+    ops::{Range, RangeInclusive},
+};
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::Style,
+    widgets::{Scrollbar, ScrollbarOrientation, ScrollbarState, StatefulWidget},
+};
+use regex::{Regex, RegexSet};
+use unicode_width::UnicodeWidthChar;
+
+use crate::{
+    folding::FoldedLog,
+    theme::{Element, Theme},
+};
+
+/// a build's log, split into lines once up front so rendering only ever touches the lines on
+/// screen
+#[derive(Default)]
+pub struct Log {
+    text: String,
+    /// byte offset of the start of each line
+    line_starts: Vec<usize>,
+    /// display width of each line
+    line_widths: Vec<u32>,
+    /// display width of the widest line
+    max_width: u32,
+    /// how many bytes of the log Jenkins has sent us, which is where to pick up from when
+    /// fetching more of it
+    fetched_bytes: u64,
+    /// only part of the log was fetched, because the whole thing was too big
+    truncated: bool,
+    /// lines matching a problem pattern, in order
+    problems: Vec<usize>,
+    /// how many lines have been checked for problems; only new lines need checking
+    scanned_lines: usize,
+    wrap_rows: WrapRows,
+}
+
+/// for the width a log was last wrapped to, the first visual row of each line (plus one past
+/// the end). it only needs recomputing when the width changes.
+#[derive(Debug, Default)]
+pub struct WrapRows(RefCell<Option<(u16, Vec<usize>)>>);
+
+impl WrapRows {
+    /// run `f` with the first visual row of each line, whose display widths are `line_widths`,
+    /// when wrapped to `width`. `line` gives the text of lines that may not wrap evenly.
+    fn with<'a, T>(
+        &self,
+        width: u16,
+        line_widths: &[u32],
+        line: impl Fn(usize) -> Cow<'a, str>,
+        f: impl FnOnce(&[usize]) -> T,
+    ) -> T {
+        let mut cache = self.0.borrow_mut();
+        if !matches!(&*cache, Some((cached_width, _)) if *cached_width == width) {
+            let columns = width.max(1) as usize;
+            let mut rows = Vec::with_capacity(line_widths.len() + 1);
+            let mut row = 0;
+            rows.push(row);
+            for (idx, line_width) in line_widths.iter().enumerate() {
+                let line_width = *line_width as usize;
+                row += if line_width <= columns {
+                    1
+                } else {
+                    let text = line(idx);
+                    if text.is_ascii() {
+                        // every row but the last is full
+                        line_width.div_ceil(columns)
+                    } else {
+                        // wide characters can leave a column free at the end of a row
+                        wrapped_row_count(&text, columns)
+                    }
+                };
+                rows.push(row);
+            }
+            *cache = Some((width, rows));
+        }
+        f(&cache.as_ref().unwrap().1)
+    }
+
+    fn clear(&self) {
+        self.0.replace(None);
+    }
+}
+
+impl From<String> for Log {
+    fn from(text: String) -> Self {
+        let mut log = Self {
+            fetched_bytes: text.len() as u64,
+            text,
+            ..Default::default()
+        };
+        log.index_from(0);
+        log
+    }
+}
+
+impl Clone for Log {
+    fn clone(&self) -> Self {
+        Self {
+            text: self.text.clone(),
+            line_starts: self.line_starts.clone(),
+            line_widths: self.line_widths.clone(),
+            max_width: self.max_width,
+            fetched_bytes: self.fetched_bytes,
+            truncated: self.truncated,
+            problems: self.problems.clone(),
+            scanned_lines: self.scanned_lines,
+            wrap_rows: WrapRows::default(),
+        }
+    }
+}
+
+impl PartialEq for Log {
+    fn eq(&self, other: &Self) -> bool {
+        self.text == other.text
+    }
+}
+
+impl Eq for Log {}
+
+// the log itself could be hundreds of MB, so don't dump it
+impl Debug for Log {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Log")
+            .field("bytes", &self.text.len())
+            .field("lines", &self.line_count())
+            .field("truncated", &self.truncated)
+            .field("problems", &self.problems.len())
+            .finish()
+    }
+}
+
+/// display width of a line, ignoring its trailing carriage return
+pub fn display_width(line: &str) -> u32 {
+    line.trim_end_matches('\r')
+        .chars()
+        .map(|c| c.width().unwrap_or(0) as u32)
+        .sum()
+}
+
+impl Log {
+    /// part of a log, along with a note saying what's missing. `fetched_bytes` is how much of the
+    /// log the server sent, including anything that was thrown away.
+    pub fn truncated(text: String, note: &str, fetched_bytes: u64) -> Self {
+        let mut log = Self::from(format!("{}\n{}", note, text));
+        log.fetched_bytes = fetched_bytes;
+        log.truncated = true;
+        log
+    }
+
+    /// index the lines starting at byte `from`, which must be the start of a line
+    fn index_from(&mut self, from: usize) {
+        let mut start = from;
+        for line in self.text[from..].split('\n') {
+            let width = display_width(line);
+            self.max_width = self.max_width.max(width);
+            self.line_starts.push(start);
+            self.line_widths.push(width);
+            start += line.len() + 1;
+        }
+        // a trailing newline doesn't start another line
+        if self.text.ends_with('\n') {
+            self.line_starts.pop();
+            self.line_widths.pop();
+        }
+        self.wrap_rows.clear();
+    }
+
+    /// add more of the log (from a running build) onto the end, re-indexing only what changed
+    pub fn append(&mut self, more: &str, fetched_bytes: u64) {
+        self.fetched_bytes = fetched_bytes;
+        if more.is_empty() {
+            return;
+        }
+        // the last line might not be finished yet, in which case it continues in `more`
+        let reindex_from = if self.text.is_empty() || self.text.ends_with('\n') {
+            self.text.len()
+        } else {
+            self.line_widths.pop();
+            self.line_starts.pop().unwrap_or_default()
+        };
+        // and if it was already checked for problems, it needs checking again
+        self.scanned_lines = self.scanned_lines.min(self.line_count());
+        let line_count = self.line_count();
+        self.problems.retain(|line| *line < line_count);
+        self.text.push_str(more);
+        self.index_from(reindex_from);
+    }
+
+    /// check the lines that haven't been checked yet for problems
+    pub fn scan_problems(&mut self, patterns: &RegexSet) {
+        for line in self.scanned_lines..self.line_count() {
+            if patterns.is_match(self.line(line)) {
+                self.problems.push(line);
+            }
+        }
+        self.scanned_lines = self.line_count();
+    }
+
+    /// use these lines as the problems instead of scanning for them
+    pub fn set_problems(&mut self, problems: Vec<usize>) {
+        self.problems = problems;
+        self.scanned_lines = self.line_count();
+    }
+
+    /// lines that match a problem pattern
+    pub fn problems(&self) -> &[usize] {
+        &self.problems
+    }
+
+    /// the first problem after `line`
+    pub fn next_problem(&self, line: usize) -> Option<usize> {
+        let idx = self.problems.partition_point(|problem| *problem <= line);
+        self.problems.get(idx).copied()
+    }
+
+    /// the last problem before `line`
+    pub fn previous_problem(&self, line: usize) -> Option<usize> {
+        let idx = self.problems.partition_point(|problem| *problem < line);
+        idx.checked_sub(1).map(|idx| self.problems[idx])
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn fetched_bytes(&self) -> u64 {
+        self.fetched_bytes
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// display width of the widest line
+    pub fn max_width(&self) -> u32 {
+        self.max_width
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// display width of the `idx`th line
+    pub fn line_width(&self, idx: usize) -> u32 {
+        self.line_widths[idx]
+    }
+
+    /// the `idx`th line, without its line ending
+    pub fn line(&self, idx: usize) -> &str {
+        let start = self.line_starts[idx];
+        let end = self
+            .line_starts
+            .get(idx + 1)
+            .map(|next| next - 1)
+            .unwrap_or(self.text.len());
+        self.text[start..end].trim_end_matches(['\r', '\n'])
+    }
+
+    /// the text of lines `lines`, with the line endings between them but not after the last
+    pub fn lines_text(&self, lines: RangeInclusive<usize>) -> &str {
+        let start = self.line_starts[*lines.start()];
+        let last = self.line(*lines.end());
+        let end = self.line_starts[*lines.end()] + last.len();
+        &self.text[start..end]
+    }
+
+    /// the line that byte `offset` is on
+    pub fn line_at(&self, offset: usize) -> usize {
+        self.line_starts
+            .partition_point(|start| *start <= offset)
+            .saturating_sub(1)
+    }
+}
+
+/// a log as it's shown: as it is, or folded
+#[derive(Debug, Clone, Copy)]
+pub enum ShownLog<'a> {
+    Whole(&'a Log),
+    /// the log, and how it's folded
+    Folded(&'a Log, &'a FoldedLog),
+}
+
+impl<'a> ShownLog<'a> {
+    pub fn line_count(&self) -> usize {
+        match self {
+            ShownLog::Whole(log) => log.line_count(),
+            ShownLog::Folded(_, folded) => folded.line_count(),
+        }
+    }
+
+    /// the `idx`th shown line, without its line ending
+    pub fn line(&self, idx: usize) -> Cow<'a, str> {
+        match self {
+            ShownLog::Whole(log) => Cow::Borrowed(log.line(idx)),
+            ShownLog::Folded(log, folded) => folded.line(log, idx),
+        }
+    }
+
+    /// the line of the original log that shown line `line` came from
+    pub fn original_line(&self, line: usize) -> usize {
+        match self {
+            ShownLog::Whole(_) => line,
+            ShownLog::Folded(_, folded) => folded.original_line(line),
+        }
+    }
+
+    /// shown lines with problems on them
+    pub fn problems(&self) -> &'a [usize] {
+        match self {
+            ShownLog::Whole(log) => log.problems(),
+            ShownLog::Folded(_, folded) => folded.problems(),
+        }
+    }
+
+    /// the first problem after `line`
+    pub fn next_problem(&self, line: usize) -> Option<usize> {
+        let idx = self.problems().partition_point(|problem| *problem <= line);
+        self.problems().get(idx).copied()
+    }
+
+    /// the last problem before `line`
+    pub fn previous_problem(&self, line: usize) -> Option<usize> {
+        let idx = self.problems().partition_point(|problem| *problem < line);
+        idx.checked_sub(1).map(|idx| self.problems()[idx])
+    }
+
+    /// display width of the widest line
+    fn max_width(&self) -> u32 {
+        match self {
+            ShownLog::Whole(log) => log.max_width(),
+            ShownLog::Folded(_, folded) => folded.max_width(),
+        }
+    }
+
+    /// how many rows the log takes up; when wrapping, that's at `width`
+    fn row_count(&self, wrap_width: Option<u16>) -> usize {
+        match wrap_width {
+            Some(width) => self.with_wrap_rows(width, |rows| *rows.last().unwrap()),
+            None => self.line_count(),
+        }
+    }
+
+    /// run `f` with the first visual row of each line when wrapped to `width`
+    fn with_wrap_rows<T>(&self, width: u16, f: impl FnOnce(&[usize]) -> T) -> T {
+        match self {
+            ShownLog::Whole(log) => {
+                log.wrap_rows
+                    .with(width, &log.line_widths, |idx| self.line(idx), f)
+            }
+            ShownLog::Folded(_, folded) => {
+                folded
+                    .wrap_rows()
+                    .with(width, folded.line_widths(), |idx| self.line(idx), f)
+            }
+        }
+    }
+
+    /// which line (and which of its wrapped rows) is on visual row `row`
+    fn wrapped_position(&self, width: u16, row: usize) -> (usize, usize) {
+        self.with_wrap_rows(width, |rows| {
+            let line = rows.partition_point(|start| *start <= row) - 1;
+            (line, row - rows[line])
+        })
+    }
+}
+
+/// where the log pane is scrolled to
+#[derive(Debug, Clone, Default)]
+pub struct LogScrollState {
+    /// first visible column
+    x: usize,
+    /// first visible row (logs can be far longer than a `Position` can address)
+    y: usize,
+    /// size of the viewport as of the last render, for paging and clamping
+    viewport: Rect,
+    /// how many rows/columns there were to scroll through as of the last render
+    content_rows: usize,
+    content_width: u32,
+    /// line the cursor is on, if it's shown
+    cursor: Option<usize>,
+    /// line a selection was started on; it runs from here to the cursor
+    anchor: Option<usize>,
+    /// when wrapping, the line to scroll to the top once the next render knows how wide the
+    /// rows are
+    pending_top: Option<usize>,
+    /// when wrapping, scroll to the cursor once the next render knows how wide the rows are
+    reveal_cursor: bool,
+}
+
+impl LogScrollState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// the first visible (column, row)
+    pub fn offset(&self) -> (usize, usize) {
+        (self.x, self.y)
+    }
+
+    pub fn set_offset(&mut self, x: usize, y: usize) {
+        (self.x, self.y) = (x, y);
+    }
+
+    /// note what there is to scroll through and how much of it fits, as of this render
+    pub fn set_content(&mut self, viewport: Rect, content_rows: usize, content_width: u32) {
+        self.viewport = viewport;
+        self.content_rows = content_rows;
+        self.content_width = content_width;
+        self.clamp();
+    }
+
+    /// the line at the top of the viewport
+    pub fn top_line(&self, log: ShownLog, wrap: bool) -> usize {
+        match self.pending_top {
+            Some(line) if wrap => line,
+            _ if wrap => log.wrapped_position(self.viewport.width, self.y).0,
+            _ => self.y,
+        }
+    }
+
+    /// the lines that are (at least partly) in the viewport
+    pub fn visible_lines(&self, log: ShownLog, wrap: bool) -> Range<usize> {
+        let bottom_row = self.y + (self.viewport.height as usize).max(1) - 1;
+        let bottom = if wrap {
+            log.wrapped_position(self.viewport.width, bottom_row).0
+        } else {
+            bottom_row
+        };
+        self.top_line(log, wrap)..(bottom + 1).min(log.line_count())
+    }
+
+    /// scroll so `line` is at the top of the viewport. when wrapping, which row that is
+    /// depends on how wide the text ends up, so it's left to the next render.
+    pub fn scroll_to_line(&mut self, line: usize, wrap: bool) {
+        if wrap {
+            self.pending_top = Some(line);
+            self.reveal_cursor = false;
+        } else {
+            self.y = line;
+        }
+    }
+
+    /// rows to move by for a page up or down
+    pub fn page_rows(&self) -> usize {
+        self.viewport.height.max(1) as usize
+    }
+
+    pub fn cursor(&self) -> Option<usize> {
+        self.cursor
+    }
+
+    /// the lines from where the selection started to the cursor, if there's a selection
+    pub fn selection(&self) -> Option<RangeInclusive<usize>> {
+        let (cursor, anchor) = (self.cursor?, self.anchor?);
+        Some(cursor.min(anchor)..=cursor.max(anchor))
+    }
+
+    /// put the cursor on `line`, scrolling just far enough to keep all of it on screen
+    pub fn move_cursor(&mut self, log: ShownLog, line: usize, wrap: bool) {
+        if log.line_count() == 0 {
+            return;
+        }
+        let line = line.min(log.line_count() - 1);
+        self.cursor = Some(line);
+        if wrap {
+            self.reveal_cursor = true;
+        } else {
+            self.reveal(line, line + 1, self.page_rows());
+        }
+    }
+
+    /// scroll just far enough to show rows `start` up to `end`, out of `height` on screen
+    fn reveal(&mut self, start: usize, end: usize, height: usize) {
+        if start < self.y {
+            self.y = start;
+        } else if end > self.y + height {
+            // lines taller than the viewport show their start
+            self.y = end.saturating_sub(height).min(start);
+        }
+    }
+
+    /// now that the log is wrapped to fit `text_area`, make the jumps that were waiting on it.
+    /// if it's narrower or wider than last time (say, a gutter came or went), the same line
+    /// stays at the top.
+    fn settle(&mut self, log: ShownLog, text_area: Rect) {
+        let width = text_area.width;
+        if self.pending_top.is_none() && self.viewport.width > 0 && self.viewport.width != width {
+            self.pending_top = Some(log.wrapped_position(self.viewport.width, self.y).0);
+        }
+        log.with_wrap_rows(width, |rows| {
+            let last = rows.len() - 1;
+            if let Some(line) = self.pending_top.take() {
+                self.y = rows[line.min(last)];
+            }
+            if let Some(line) = self.cursor.filter(|_| self.reveal_cursor) {
+                let (start, end) = (rows[line.min(last)], rows[(line + 1).min(last)]);
+                self.reveal(start, end, (text_area.height as usize).max(1));
+            }
+        });
+        self.reveal_cursor = false;
+    }
+
+    /// start a selection at the cursor, or drop the one that's being made
+    pub fn toggle_selection(&mut self) {
+        self.anchor = match self.anchor {
+            Some(_) => None,
+            None => self.cursor,
+        };
+    }
+
+    /// stop showing the cursor, and drop the selection along with it
+    pub fn hide_cursor(&mut self) {
+        (self.cursor, self.anchor) = (None, None);
+    }
+
+    /// move the cursor and selection to where their lines ended up after the log changed
+    pub fn remap_cursor(&mut self, remap: impl Fn(usize) -> usize) {
+        self.cursor = self.cursor.map(&remap);
+        self.anchor = self.anchor.map(&remap);
+    }
+
+    pub fn scroll_up(&mut self) {
+        self.y = self.y.saturating_sub(1);
+    }
+
+    pub fn scroll_down(&mut self) {
+        self.y = self.y.saturating_add(1);
+        self.clamp();
+    }
+
+    pub fn scroll_left(&mut self) {
+        self.x = self.x.saturating_sub(1);
+    }
+
+    pub fn scroll_right(&mut self) {
+        self.x = self.x.saturating_add(1);
+        self.clamp();
+    }
+
+    pub fn scroll_page_up(&mut self) {
+        self.y = self.y.saturating_sub(self.page_rows());
+    }
+
+    pub fn scroll_page_down(&mut self) {
+        self.y = self.y.saturating_add(self.page_rows());
+        self.clamp();
+    }
+
+    pub fn scroll_to_top(&mut self) {
+        self.y = 0;
+    }
+
+    pub fn scroll_to_bottom(&mut self) {
+        self.y = usize::MAX;
+        self.clamp();
+    }
+
+    /// keep the last row/column from scrolling past the bottom/right of the viewport
+    fn clamp(&mut self) {
+        self.y = self.y.min(
+            self.content_rows
+                .saturating_sub(self.viewport.height as usize),
+        );
+        self.x = self
+            .x
+            .min((self.content_width as usize).saturating_sub(self.viewport.width as usize));
+    }
+}
+
+/// lines of a log matching a search, optionally only within some of the original log's lines
+#[derive(Debug, Clone)]
+pub struct LogSearch {
+    pub pattern: Regex,
+    /// lines of the original log to search within, if not all of them
+    within: Option<RangeInclusive<usize>>,
+    /// matching lines, in order
+    matches: Vec<usize>,
+}
+
+impl LogSearch {
+    pub fn new(pattern: Regex, within: Option<RangeInclusive<usize>>) -> Self {
+        Self {
+            pattern,
+            within,
+            matches: vec![],
+        }
+    }
+
+    /// find the matching lines of `log`, whose lines came from `original_line` of the original
+    pub fn search(&mut self, log: ShownLog, original_line: impl Fn(usize) -> usize) {
+        self.matches = (0..log.line_count())
+            .filter(|line| {
+                self.within
+                    .as_ref()
+                    .is_none_or(|within| within.contains(&original_line(*line)))
+                    && self.pattern.is_match(&log.line(*line))
+            })
+            .collect();
+    }
+
+    pub fn matches(&self) -> &[usize] {
+        &self.matches
+    }
+
+    /// the first match after `line`
+    pub fn next_match(&self, line: usize) -> Option<usize> {
+        let idx = self.matches.partition_point(|m| *m <= line);
+        self.matches.get(idx).copied()
+    }
+
+    /// the last match before `line`
+    pub fn previous_match(&self, line: usize) -> Option<usize> {
+        let idx = self.matches.partition_point(|m| *m < line);
+        idx.checked_sub(1).map(|idx| self.matches[idx])
+    }
+}
+
+/// columns taken up by the marker and the space after it
+const GUTTER_WIDTH: u16 = 2;
+
+/// renders only the rows of a `Log` that are on screen, plus scrollbars
+pub struct LogView<'a> {
+    log: ShownLog<'a>,
+    theme: &'a Theme,
+    wrap: bool,
+    line_numbers: bool,
+    /// lines to highlight as search matches
+    matches: &'a [usize],
+}
+
+impl<'a> LogView<'a> {
+    pub fn new(log: ShownLog<'a>, theme: &'a Theme) -> Self {
+        Self {
+            log,
+            theme,
+            wrap: false,
+            line_numbers: false,
+            matches: &[],
+        }
+    }
+
+    pub fn wrap(mut self, wrap: bool) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    /// show line numbers in the gutter, numbered as in the original log
+    pub fn line_numbers(mut self, line_numbers: bool) -> Self {
+        self.line_numbers = line_numbers;
+        self
+    }
+
+    pub fn matches(mut self, matches: &'a [usize]) -> Self {
+        self.matches = matches;
+        self
+    }
+}
+
+/// write `text` into the buffer at row `y`, skipping the first `skip` columns
+pub fn render_row(buf: &mut Buffer, area: Rect, y: u16, text: &str, skip: usize, style: Style) {
+    let mut skipped = 0;
+    let visible = text
+        .char_indices()
+        .find(|(_, c)| {
+            let found = skipped >= skip;
+            skipped += c.width().unwrap_or(0);
+            found
+        })
+        .map(|(idx, _)| &text[idx..])
+        .unwrap_or_default();
+    buf.set_stringn(area.x, y, visible, area.width as usize, style);
+}
+
+/// split off the part of `text` that fits in `width` columns. it's always at least one
+/// character, even one wider than `width`, so wrapping gets through the whole line.
+fn split_at_width(text: &str, width: usize) -> (&str, &str) {
+    let mut used = 0;
+    for (idx, c) in text.char_indices() {
+        used += c.width().unwrap_or(0);
+        if used > width {
+            let idx = if idx == 0 { c.len_utf8() } else { idx };
+            return text.split_at(idx);
+        }
+    }
+    (text, "")
+}
+
+/// how many rows `text` takes up when it's wrapped to `width` columns
+fn wrapped_row_count(text: &str, width: usize) -> usize {
+    let mut rows = 1;
+    let mut rest = split_at_width(text, width).1;
+    while !rest.is_empty() {
+        rows += 1;
+        rest = split_at_width(rest, width).1;
+    }
+    rows
+}
+
+impl LogView<'_> {
+    /// the number to show for `line`, starting at 1
+    fn line_number(&self, line: usize) -> usize {
+        self.log.original_line(line) + 1
+    }
+
+    /// how `line` stands out: as a search match, selected, and/or under the cursor
+    fn line_style(&self, line: usize, state: &LogScrollState) -> Style {
+        let mut style = Style::default();
+        if self.matches.binary_search(&line).is_ok() {
+            style = style.patch(self.theme.style(Element::Match));
+        }
+        if state
+            .selection()
+            .is_some_and(|selection| selection.contains(&line))
+        {
+            style = style.patch(self.theme.style(Element::Selection));
+        }
+        if state.cursor == Some(line) {
+            style = style.patch(self.theme.style(Element::Cursor));
+        }
+        style
+    }
+}
+
+impl StatefulWidget for LogView<'_> {
+    type State = LogScrollState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        // leave room for the line numbers and problem markers, if there are any, and the
+        // scrollbars
+        let number_width = if self.line_numbers {
+            let last = self.line_number(self.log.line_count().saturating_sub(1));
+            last.to_string().len() as u16 + 1
+        } else {
+            0
+        };
+        let marker_width = if self.log.problems().is_empty() {
+            0
+        } else {
+            GUTTER_WIDTH
+        };
+        let gutter = number_width + marker_width;
+        let text_area = Rect {
+            x: area.x + gutter.min(area.width),
+            width: area.width.saturating_sub(gutter + 1),
+            height: area.height.saturating_sub(if self.wrap { 0 } else { 1 }),
+            ..area
+        };
+        let wrap_width = self.wrap.then_some(text_area.width);
+        if self.wrap {
+            state.settle(self.log, text_area);
+        }
+        state.set_content(
+            text_area,
+            self.log.row_count(wrap_width),
+            if self.wrap { 0 } else { self.log.max_width() },
+        );
+
+        let draw_gutter = |buf: &mut Buffer, y: u16, line: usize| {
+            if number_width > 0 {
+                let style = if state.cursor == Some(line) {
+                    self.theme.style(Element::CursorLineNumber)
+                } else {
+                    self.theme.style(Element::LineNumber)
+                };
+                let number = format!(
+                    "{:>width$} ",
+                    self.line_number(line),
+                    width = number_width as usize - 1
+                );
+                buf.set_stringn(area.x, y, number, area.width as usize, style);
+            }
+            if marker_width > 0 && self.log.problems().binary_search(&line).is_ok() {
+                buf.set_string(
+                    area.x + number_width,
+                    y,
+                    self.theme.symbols.problem,
+                    self.theme.style(Element::Problem),
+                );
+            }
+        };
+        // highlights cover the whole row, not just the text on it
+        let draw_row = |buf: &mut Buffer, y: u16, text: &str, skip: usize, style: Style| {
+            if style != Style::default() {
+                buf.set_style(
+                    Rect {
+                        y,
+                        height: 1,
+                        ..text_area
+                    },
+                    style,
+                );
+            }
+            render_row(buf, text_area, y, text, skip, style);
+        };
+        match wrap_width {
+            Some(width) => {
+                let (mut line, sub_row) = self.log.wrapped_position(width, state.y);
+                let mut text = self.log.line(line);
+                // where the rest of the line starts in `text`
+                let mut rest = 0;
+                for _ in 0..sub_row {
+                    rest = text.len() - split_at_width(&text[rest..], width as usize).1.len();
+                }
+                let mut first_row = sub_row == 0;
+                for y in text_area.top()..text_area.bottom() {
+                    if first_row {
+                        draw_gutter(buf, y, line);
+                    }
+                    let (chunk, remainder) = split_at_width(&text[rest..], width as usize);
+                    draw_row(buf, y, chunk, 0, self.line_style(line, state));
+                    first_row = remainder.is_empty();
+                    if !remainder.is_empty() {
+                        rest = text.len() - remainder.len();
+                        continue;
+                    }
+                    line += 1;
+                    if line >= self.log.line_count() {
+                        break;
+                    }
+                    (text, rest) = (self.log.line(line), 0);
+                }
+            }
+            None => {
+                let lines =
+                    (state.y..self.log.line_count()).zip(text_area.top()..text_area.bottom());
+                for (line, y) in lines {
+                    draw_gutter(buf, y, line);
+                    draw_row(
+                        buf,
+                        y,
+                        &self.log.line(line),
+                        state.x,
+                        self.line_style(line, state),
+                    );
+                }
+            }
+        }
+
+        if state.content_rows > text_area.height as usize {
+            let mut vertical = ScrollbarState::new(state.content_rows - text_area.height as usize)
+                .position(state.y);
+            Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                .symbols(self.theme.symbols.vertical_scrollbar.clone())
+                .render(area, buf, &mut vertical);
+        }
+        if state.content_width > text_area.width as u32 {
+            let mut horizontal =
+                ScrollbarState::new((state.content_width - text_area.width as u32) as usize)
+                    .position(state.x);
+            Scrollbar::new(ScrollbarOrientation::HorizontalBottom)
+                .symbols(self.theme.symbols.horizontal_scrollbar.clone())
+                .render(
+                    Rect {
+                        x: text_area.x,
+                        width: text_area.width,
+                        ..area
+                    },
+                    buf,
+                    &mut horizontal,
+                );
+        }
+    }
+}
@@ -1,65 +1,219 @@
-use std::sync::LazyLock;
-use std::{error::Error, io};
+use std::{error::Error, io, path::PathBuf};
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use event::{Event, EventHandler};
-use expanduser::expanduser;
-use handler::handle_key_events;
+use handler::{handle_key_events, handle_mouse_events};
 use ratatui::{prelude::CrosstermBackend, Terminal};
+use regex::Regex;
 
 use crate::app::App;
+use crate::causes::Analysis;
+use crate::config::{ConfigSource, DEFAULT_APP_CONFIG_PATH};
+use crate::jenkins::{BuildState, JenkinsResult};
+use crate::record::HttpMode;
 use crate::tui::Tui;
 
 pub mod app;
+pub mod cache;
+pub mod causes;
+pub mod config;
+pub mod credentials;
+pub mod diff;
 pub mod event;
+pub mod export;
+pub mod folding;
 pub mod handler;
+pub mod health;
 pub mod jenkins;
+pub mod keymap;
+pub mod layout;
+pub mod log_store;
+pub mod log_view;
+pub mod palette;
+pub mod record;
+pub mod session;
+pub mod theme;
 pub mod tui;
 pub mod ui;
 
-static DEFAULT_JENKINS_CONFIG_PATH: LazyLock<String> = LazyLock::new(|| {
-    expanduser("~/.config/jenkins_jobs/jenkins_jobs.ini")
-        .unwrap()
-        .to_str()
-        .unwrap()
-        .to_string()
-});
-
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
-    #[arg(short, long, default_value_t = DEFAULT_JENKINS_CONFIG_PATH.to_string())]
-    jenkins_config_path: String,
+    /// path to the app config file (TOML, or YAML with a .yaml/.yml extension)
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+    /// path to a JJB config file to use instead of the app config's `jjb_configs`
+    #[arg(short, long, visible_alias = "conf", value_name = "PATH")]
+    jenkins_config_path: Option<PathBuf>,
+    /// only use this server (section of the JJB config file)
+    #[arg(long)]
+    section: Option<String>,
+    /// save every response (with credentials scrubbed) into this directory
+    #[arg(long, value_name = "DIR", conflicts_with = "replay")]
+    record: Option<PathBuf>,
+    /// serve responses previously saved with `--record` instead of using the network
+    #[arg(long, value_name = "DIR")]
+    replay: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// look for known failure causes in many builds' logs and count them up, rather than
+    /// starting the TUI
+    Analyze {
+        /// only analyze jobs whose name matches this regex
+        #[arg(long, value_name = "REGEX")]
+        job: Option<Regex>,
+        /// analyze every build, not just failed ones
+        #[arg(long)]
+        all: bool,
+    },
+}
+
+/// analyze builds on every configured server, printing what was found
+async fn analyze(
+    config_source: &ConfigSource,
+    http_mode: HttpMode,
+    job: Option<Regex>,
+    all: bool,
+) -> Result<(), Box<dyn Error>> {
+    let config = config_source.load()?;
+    let causes = config.failure_causes()?;
+    if causes.is_empty() {
+        return Err(format!(
+            "No failure causes configured (see failure_causes, {})",
+            config.failure_causes.display()
+        )
+        .into());
+    }
+    let cache = config.cache();
+    let mut analysis = Analysis::default();
+    for (name, mut server) in config
+        .load_servers(config_source.section.as_deref())?
+        .servers
+    {
+        server.set_http_mode(http_mode.clone());
+        let include = |result: &JenkinsResult| {
+            (all || result.build_state == BuildState::Failure)
+                && job.as_ref().is_none_or(|job| job.is_match(&result.name))
+        };
+        if let Err(e) = analysis
+            .add_server(
+                &name,
+                &mut server,
+                &causes,
+                cache.as_ref(),
+                config.logs.limit(),
+                include,
+            )
+            .await
+        {
+            analysis.errors.push(format!("[{}]: {}", name, e));
+        }
+    }
+    print!("{}", analysis);
+    Ok(())
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
     let args = Args::parse();
-    let mut app = App::new(args.jenkins_config_path);
+    let http_mode = match (args.record, args.replay) {
+        (Some(dir), _) => HttpMode::Record(dir),
+        (_, Some(dir)) => HttpMode::Replay(dir),
+        _ => HttpMode::Live,
+    };
+    let config_source = ConfigSource {
+        required: args.config.is_some(),
+        path: args.config.unwrap_or(DEFAULT_APP_CONFIG_PATH.clone()),
+        jenkins_config_path: args.jenkins_config_path,
+        section: args.section,
+    };
+    if let Some(Command::Analyze { job, all }) = args.command {
+        return analyze(&config_source, http_mode, job, all).await;
+    }
+    let mut app = App::new(config_source, http_mode);
 
     let backend = CrosstermBackend::new(io::stdout());
     let terminal = Terminal::new(backend)?;
     let events = EventHandler::new(250);
-    let mut tui = Tui::new(terminal, events);
+    let mut tui = Tui::new(terminal, events).mouse_capture(app.config.mouse);
     tui.init()?;
+    for event in app.restore_session() {
+        tui.events.push_event(event);
+    }
+    if let Some(event) = app.select_only_server() {
+        tui.events.push_event(event);
+    }
 
+    let sender = tui.events.sender();
     while app.running {
         tui.draw(&mut app)?;
         match tui.events.next().await? {
-            Event::Tick => app.tick(),
+            Event::Tick => {
+                if let Some(event) = app.tick() {
+                    tui.events.push_event(event);
+                }
+            }
             Event::Key(key_event) => {
                 // keypress may cause additional events for processing
                 if let Ok(Some(event)) = handle_key_events(key_event, &mut app).await {
                     tui.events.push_event(event);
                 }
             }
-            Event::RefreshJobsForServer => app.refresh_jobs().await?,
-            Event::RefreshLogsForJob => app.refresh_logs().await?,
+            Event::Mouse(mouse_event) => {
+                if let Ok(Some(event)) = handle_mouse_events(mouse_event, &mut app).await {
+                    tui.events.push_event(event);
+                }
+            }
+            // fetching happens in the background, so waiting on a slow server doesn't freeze
+            // the UI
+            Event::RefreshJobsForServer => app.refresh_jobs(&sender),
+            Event::RefreshLogsForJob => app.refresh_logs(false, &sender),
+            Event::LoadFullLogsForJob => app.refresh_logs(true, &sender),
+            Event::JobsFetched(fetched) => {
+                if let Some(event) = app.jobs_fetched(*fetched) {
+                    tui.events.push_event(event);
+                }
+            }
+            Event::LogsFetched(fetched) => app.logs_fetched(*fetched, &sender),
+            Event::DiffLogs => app.diff_with_marked(&sender),
+            Event::DiffComputed(diff) => app.diff_computed(*diff),
+            Event::PageLogs => {
+                if let Some(logs) = app.logs_for_pager() {
+                    if let Err(e) = tui.suspend(|| export::page(&logs)).await? {
+                        app.set_status(&format!("Failed to run the pager: {}", e));
+                    }
+                }
+            }
+            Event::EditLogs => match app.logs_for_editor() {
+                Ok(Some((file, line))) => {
+                    if let Err(e) = tui.suspend(|| export::edit(file.path(), line)).await? {
+                        app.set_status(&format!("Failed to run the editor: {}", e));
+                    }
+                    if let Err(e) = file.close() {
+                        app.set_status(&format!("Failed to remove the editor's temp file: {}", e));
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => app.set_status(&format!("Failed to write logs for the editor: {}", e)),
+            },
+            Event::ToggleMouseCapture => match tui.toggle_mouse_capture() {
+                Ok(true) => app.set_status("Mouse captured; M lets the terminal select text"),
+                Ok(false) => app.set_status("Mouse released for selecting text; M captures it"),
+                Err(e) => app.set_status(&format!("Failed to toggle mouse capture: {}", e)),
+            },
+            Event::RefreshAllJobs => app.refresh_all_jobs(&sender),
+            Event::TriggerBuild(trigger) => app.trigger_build(trigger, &sender),
+            Event::BuildTriggered(trigger, result) => app.build_triggered(trigger, result),
             // TODO: potentially handle other events
             _ => {}
         }
     }
 
     tui.exit()?;
+    app.save_session()?;
     Ok(())
 }
@@ -0,0 +1,231 @@
+use std::cmp::Reverse;
+
+use crate::keymap::{Action, ACTIONS};
+
+/// something the command palette can run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// whatever the action does in the active pane
+    Action(Action),
+    /// refresh every server's jobs
+    RefreshAll,
+    /// start a build of a job, or the selected build's job
+    TriggerBuild(Option<String>),
+    /// select a server by name
+    Server(String),
+    /// select the latest build of a job by name
+    Job(String),
+}
+
+/// what a command's argument is, if it takes one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Argument {
+    None,
+    Server,
+    Job,
+    OptionalJob,
+}
+
+/// commands that aren't just actions, with what they take and what they do
+const COMMANDS: [(&str, Argument, &str); 4] = [
+    ("refresh-all", Argument::None, "refresh every server's jobs"),
+    (
+        "trigger-build",
+        Argument::OptionalJob,
+        "start a build of a job (or the selected one)",
+    ),
+    ("server", Argument::Server, "switch to a server"),
+    ("job", Argument::Job, "select the latest build of a job"),
+];
+
+/// how well `pattern` matches `candidate`, ignoring case, if its characters all appear in
+/// order. consecutive characters and ones at the start of words count for more.
+pub fn fuzzy_score(pattern: &str, candidate: &str) -> Option<i64> {
+    let mut pattern = pattern.chars().flat_map(char::to_lowercase).peekable();
+    let mut score = 0;
+    let mut previous: Option<char> = None;
+    let mut matched_previous = false;
+    for c in candidate.chars().flat_map(char::to_lowercase) {
+        let Some(&wanted) = pattern.peek() else {
+            break;
+        };
+        if c == wanted {
+            pattern.next();
+            score += 1;
+            if matched_previous {
+                score += 5;
+            }
+            if previous.is_none_or(|p| matches!(p, '-' | '_' | ' ' | '/' | '.')) {
+                score += 3;
+            }
+            matched_previous = true;
+        } else {
+            matched_previous = false;
+        }
+        previous = Some(c);
+    }
+    pattern.peek().is_none().then_some(score)
+}
+
+/// a suggested completion of what's been typed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    /// the whole command line it completes to
+    pub text: String,
+    pub description: String,
+}
+
+/// `candidates` matching `pattern`, best first, each completing to `prefix` followed by it
+fn best_matches<'a>(
+    pattern: &str,
+    prefix: &str,
+    candidates: impl Iterator<Item = (&'a str, &'a str)>,
+) -> Vec<Suggestion> {
+    let mut scored: Vec<(i64, Suggestion)> = candidates
+        .filter_map(|(candidate, description)| {
+            let score = fuzzy_score(pattern, candidate)?;
+            Some((
+                score,
+                Suggestion {
+                    text: format!("{}{}", prefix, candidate),
+                    description: description.to_string(),
+                },
+            ))
+        })
+        .collect();
+    // ties go to the shortest, since that's the closest match
+    scored.sort_by_key(|(score, suggestion)| (Reverse(*score), suggestion.text.len()));
+    scored
+        .into_iter()
+        .map(|(_, suggestion)| suggestion)
+        .collect()
+}
+
+fn argument_of(command: &str) -> Option<Argument> {
+    COMMANDS
+        .iter()
+        .find(|(name, _, _)| *name == command)
+        .map(|(_, argument, _)| *argument)
+        .or_else(|| action_named(command).map(|_| Argument::None))
+}
+
+fn action_named(name: &str) -> Option<Action> {
+    ACTIONS
+        .iter()
+        .find(|(action, action_name, _)| *action_name == name && *action != Action::CommandPalette)
+        .map(|(action, _, _)| *action)
+}
+
+/// the `:` command palette: a command being typed, and which suggestion is selected
+#[derive(Debug, Default)]
+pub struct Palette {
+    pub input: String,
+    pub selected: usize,
+}
+
+impl Palette {
+    /// commands matching what's been typed, or once a command that takes an argument has been
+    /// typed, the `servers` or `jobs` matching its argument
+    pub fn suggestions(&self, servers: &[&str], jobs: &[&str]) -> Vec<Suggestion> {
+        let Some((command, argument)) = self.input.split_once(' ') else {
+            let commands = COMMANDS
+                .iter()
+                .map(|(name, _, description)| (*name, *description));
+            let actions = ACTIONS
+                .iter()
+                .filter(|(action, _, _)| *action != Action::CommandPalette)
+                .map(|(_, name, description)| (*name, *description));
+            return best_matches(&self.input, "", commands.chain(actions));
+        };
+        let (candidates, description) = match argument_of(command) {
+            Some(Argument::Server) => (servers, "server"),
+            Some(Argument::Job | Argument::OptionalJob) => (jobs, "job"),
+            _ => return vec![],
+        };
+        best_matches(
+            argument.trim(),
+            &format!("{} ", command),
+            candidates.iter().map(|candidate| (*candidate, description)),
+        )
+    }
+
+    pub fn select_next(&mut self, suggestions: usize) {
+        if suggestions > 0 {
+            self.selected = (self.selected + 1) % suggestions;
+        }
+    }
+
+    pub fn select_previous(&mut self, suggestions: usize) {
+        if suggestions > 0 {
+            self.selected = (self.selected + suggestions - 1) % suggestions;
+        }
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.input.push(c);
+        self.selected = 0;
+    }
+
+    pub fn pop(&mut self) {
+        self.input.pop();
+        self.selected = 0;
+    }
+
+    /// fill in the selected suggestion, ready for an argument if it takes one
+    pub fn complete(&mut self, suggestions: &[Suggestion]) {
+        let Some(suggestion) = suggestions.get(self.selected) else {
+            return;
+        };
+        self.input = suggestion.text.clone();
+        if !self.input.contains(' ')
+            && argument_of(&self.input).is_some_and(|argument| argument != Argument::None)
+        {
+            self.input.push(' ');
+        }
+        self.selected = 0;
+    }
+
+    /// the command to run: the selected suggestion, or what's been typed if nothing matches it.
+    /// `trigger-build` only ever starts a build of exactly the job typed (or with none, the
+    /// selected build's job), rather than whichever one matches best.
+    pub fn command(&self, suggestions: &[Suggestion]) -> Result<Command, String> {
+        let text = suggestions
+            .get(self.selected)
+            .map_or(self.input.as_str(), |suggestion| suggestion.text.as_str());
+        match parse(text)? {
+            Command::TriggerBuild(_) => {
+                let job = self
+                    .input
+                    .split_once(' ')
+                    .map(|(_, argument)| argument.trim())
+                    .filter(|argument| !argument.is_empty());
+                Ok(Command::TriggerBuild(job.map(str::to_string)))
+            }
+            command => Ok(command),
+        }
+    }
+}
+
+/// parse a whole command line, like `server prod` or `toggle-wrap`
+fn parse(input: &str) -> Result<Command, String> {
+    let (command, argument) = match input.trim().split_once(' ') {
+        Some((command, argument)) => (command, Some(argument.trim()).filter(|a| !a.is_empty())),
+        None => (input.trim(), None),
+    };
+    let Some(kind) = argument_of(command) else {
+        return Err(format!("Unknown command {:?}", command));
+    };
+    match (kind, argument) {
+        (Argument::None, None) => Ok(match command {
+            "refresh-all" => Command::RefreshAll,
+            _ => Command::Action(action_named(command).expect("checked by argument_of")),
+        }),
+        (Argument::None, Some(_)) => Err(format!("{} doesn't take an argument", command)),
+        (Argument::OptionalJob, argument) => {
+            Ok(Command::TriggerBuild(argument.map(str::to_string)))
+        }
+        (Argument::Server, Some(server)) => Ok(Command::Server(server.to_string())),
+        (Argument::Job, Some(job)) => Ok(Command::Job(job.to_string())),
+        (Argument::Server | Argument::Job, None) => Err(format!("{} needs a name", command)),
+    }
+}
@@ -0,0 +1,189 @@
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use reqwest::{Response, ResponseBuilderExt, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+/// what secrets are replaced with
+static SCRUBBED: &str = "[scrubbed]";
+
+/// headers that may carry credentials or session state, and so never get written to disk
+static SCRUBBED_HEADERS: [&str; 4] = [
+    "set-cookie",
+    "authorization",
+    "proxy-authorization",
+    "x-jenkins-session",
+];
+
+/// how requests to the Jenkins servers should be made
+#[derive(Debug, Clone, Default)]
+pub enum HttpMode {
+    /// talk to the network like normal
+    #[default]
+    Live,
+    /// talk to the network, but save every response into this directory
+    Record(PathBuf),
+    /// never talk to the network; serve previously recorded responses from this directory
+    Replay(PathBuf),
+}
+
+/// a response saved to disk
+#[derive(Debug, Serialize, Deserialize)]
+struct Recording {
+    /// URL the response came from, without any userinfo
+    url: String,
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: String,
+}
+
+/// remove any userinfo from the URL so it's safe to persist
+fn scrub_url(url: &Url) -> Url {
+    let mut url = url.clone();
+    // these only fail for URLs that can't have userinfo in the first place
+    let _ = url.set_username("");
+    let _ = url.set_password(None);
+    url
+}
+
+/// how much of a URL is kept, readably, at the start of its file name
+const READABLE_NAME_LEN: usize = 60;
+
+/// turn a URL into a file name (without userinfo). it's a readable but lossy prefix followed by
+/// a hash of the whole URL, so every distinct URL gets its own file and names stay well under
+/// file system limits however long the URL is.
+pub fn url_file_name(url: &Url) -> String {
+    let url = scrub_url(url);
+    let readable: String = format!(
+        "{}{}",
+        url.host_str().unwrap_or_default(),
+        url.path().trim_end_matches('/')
+    )
+    .chars()
+    .map(|c| {
+        if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+            c
+        } else {
+            '_'
+        }
+    })
+    .take(READABLE_NAME_LEN)
+    .collect();
+    let hash: String = Sha256::digest(url.as_str())
+        .iter()
+        .take(16)
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("{}-{}", readable, hash)
+}
+
+/// where the recording for a URL lives, so every distinct request gets its own recording
+fn recording_path(dir: &Path, url: &Url) -> PathBuf {
+    dir.join(format!("{}.json", url_file_name(url)))
+}
+
+/// replace every JSON string that's exactly one of `secrets`; returns whether any were
+fn scrub_json(value: &mut Value, secrets: &[&str]) -> bool {
+    match value {
+        Value::String(s) if secrets.contains(&s.as_str()) => {
+            *s = SCRUBBED.to_string();
+            true
+        }
+        Value::Array(values) => {
+            values
+                .iter_mut()
+                .map(|v| scrub_json(v, secrets))
+                .filter(|scrubbed| *scrubbed)
+                .count()
+                > 0
+        }
+        Value::Object(fields) => {
+            fields
+                .values_mut()
+                .map(|v| scrub_json(v, secrets))
+                .filter(|scrubbed| *scrubbed)
+                .count()
+                > 0
+        }
+        _ => false,
+    }
+}
+
+/// the body with any JSON fields that are exactly a credential replaced. anything else is left
+/// alone: a short user name could turn up anywhere in a log or feed.
+fn scrub_body(body: &str, secrets: &[&str]) -> String {
+    let secrets: Vec<&str> = secrets.iter().copied().filter(|s| !s.is_empty()).collect();
+    if let Ok(mut value) = serde_json::from_str::<Value>(body) {
+        if scrub_json(&mut value, &secrets) {
+            if let Ok(scrubbed) = serde_json::to_string(&value) {
+                return scrubbed;
+            }
+        }
+    }
+    body.to_string()
+}
+
+/// consume the response, write it to `dir` with any secrets removed, then rebuild an equivalent
+/// response for the caller
+pub async fn record(
+    dir: &Path,
+    url: &Url,
+    response: Response,
+    secrets: &[&str],
+) -> Result<Response, Box<dyn Error>> {
+    let status = response.status().as_u16();
+    let headers: Vec<(String, String)> = response
+        .headers()
+        .iter()
+        .filter(|(k, _)| !SCRUBBED_HEADERS.contains(&k.as_str()))
+        .filter_map(|(k, v)| {
+            let v = v.to_str().ok()?;
+            let v = if !v.is_empty() && secrets.contains(&v) {
+                SCRUBBED
+            } else {
+                v
+            };
+            Some((k.to_string(), v.to_string()))
+        })
+        .collect();
+    let body = response.text().await?;
+
+    let scrubbed_body = scrub_body(&body, secrets);
+    let recording = Recording {
+        url: scrub_url(url).to_string(),
+        status,
+        headers,
+        body: scrubbed_body,
+    };
+    fs::create_dir_all(dir)?;
+    fs::write(
+        recording_path(dir, url),
+        serde_json::to_string_pretty(&recording)?,
+    )?;
+
+    // hand back the unscrubbed body so recording doesn't change what the app sees
+    into_response(Recording { body, ..recording })
+}
+
+/// serve a previously recorded response for `url` from `dir`
+pub fn replay(dir: &Path, url: &Url) -> Result<Response, Box<dyn Error>> {
+    let path = recording_path(dir, url);
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("No recording for {} at {}: {}", url, path.display(), e))?;
+    into_response(serde_json::from_str(&content)?)
+}
+
+fn into_response(recording: Recording) -> Result<Response, Box<dyn Error>> {
+    let mut builder = http::Response::builder()
+        .status(recording.status)
+        .url(Url::parse(&recording.url)?);
+    for (k, v) in recording.headers {
+        builder = builder.header(k, v);
+    }
+    Ok(builder.body(recording.body)?.into())
+}
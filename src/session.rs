@@ -0,0 +1,40 @@
+use std::{error::Error, fs, path::Path};
+
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+use crate::layout::PaneLayout;
+
+/// what the user was looking at when they quit, so they can pick up where they left off
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Session {
+    /// name of the selected server
+    pub server: Option<String>,
+    /// whether the all-servers view was selected instead
+    #[serde(default)]
+    pub all_servers: bool,
+    /// link of the selected build; indexes change as builds come and go, and servers running the
+    /// same jobs have builds with the same names and numbers
+    #[serde(default)]
+    pub build_link: Option<Url>,
+    pub active_pane: i8,
+    pub wrap_logs: bool,
+    /// log scroll offset as (column, row)
+    pub log_scroll: (usize, usize),
+    /// how the panes were laid out, if it was saved
+    pub layout: Option<PaneLayout>,
+}
+
+impl Session {
+    pub fn load<P: AsRef<Path>>(path: P) -> Option<Self> {
+        serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        if let Some(parent) = path.as_ref().parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
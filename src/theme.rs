@@ -0,0 +1,360 @@
+use std::{collections::HashMap, env, error::Error, fs, path::Path, str::FromStr};
+
+use ratatui::{
+    style::{Color, Modifier, Style},
+    symbols::{border, scrollbar},
+};
+use serde::Deserialize;
+
+use crate::jenkins::BuildState;
+
+/// everything in the UI that has a style of its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Element {
+    /// the active pane's border, the selected server, and popups
+    Accent,
+    Success,
+    Failure,
+    /// builds that are still running
+    Running,
+    Aborted,
+    NotBuilt,
+    /// known failure causes found in a build's logs
+    Cause,
+    /// jobs shown from the cache until they're refreshed
+    Stale,
+    /// servers whose TLS certificates aren't verified
+    Insecure,
+    HealthOk,
+    HealthDegraded,
+    /// unreachable, or failing to authenticate
+    HealthDown,
+    /// marks log lines that look like problems
+    Problem,
+    /// search matches in the logs
+    Match,
+    /// selected log lines
+    Selection,
+    /// the log line under the cursor
+    Cursor,
+    LineNumber,
+    CursorLineNumber,
+    /// lines in a diff that changed, were removed, or were added
+    Changed,
+    Removed,
+    Added,
+    /// keys in the help overlay
+    Key,
+    /// descriptions in the command palette
+    Description,
+}
+
+/// a built-in theme
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Preset {
+    Default,
+    /// blue and orange rather than green and red, with symbols for build states
+    ColorBlind,
+    /// no colors at all, just bold, dim, reversed, and so on, with symbols for build states
+    NoColor,
+}
+
+const DEFAULT_STYLES: &[(Element, &str)] = &[
+    (Element::Accent, "magenta"),
+    (Element::Success, "green"),
+    (Element::Failure, "red"),
+    (Element::Running, "yellow"),
+    (Element::Aborted, "gray"),
+    (Element::NotBuilt, "gray"),
+    (Element::Cause, "cyan"),
+    (Element::Stale, "yellow"),
+    (Element::Insecure, "red"),
+    (Element::HealthOk, "green"),
+    (Element::HealthDegraded, "yellow"),
+    (Element::HealthDown, "red"),
+    (Element::Problem, "red"),
+    (Element::Match, "yellow"),
+    (Element::Selection, "on dark_gray"),
+    (Element::Cursor, "reversed"),
+    (Element::LineNumber, "dark_gray"),
+    (Element::CursorLineNumber, "yellow"),
+    (Element::Changed, "yellow"),
+    (Element::Removed, "red"),
+    (Element::Added, "green"),
+    (Element::Key, "cyan"),
+    (Element::Description, "dark_gray"),
+];
+
+/// the Okabe-Ito blue and orange, which stay apart with any kind of color blindness
+const COLOR_BLIND_STYLES: &[(Element, &str)] = &[
+    (Element::Accent, "magenta"),
+    (Element::Success, "#56b4e9"),
+    (Element::Failure, "bold #e69f00"),
+    (Element::Running, "white"),
+    (Element::Aborted, "gray"),
+    (Element::NotBuilt, "gray"),
+    (Element::Cause, "#cc79a7"),
+    (Element::Stale, "#f0e442"),
+    (Element::Insecure, "bold #e69f00"),
+    (Element::HealthOk, "#56b4e9"),
+    (Element::HealthDegraded, "#f0e442"),
+    (Element::HealthDown, "bold #e69f00"),
+    (Element::Problem, "bold #e69f00"),
+    (Element::Match, "bold #f0e442"),
+    (Element::Selection, "on dark_gray"),
+    (Element::Cursor, "reversed"),
+    (Element::LineNumber, "dark_gray"),
+    (Element::CursorLineNumber, "#f0e442"),
+    (Element::Changed, "#cc79a7"),
+    (Element::Removed, "#e69f00"),
+    (Element::Added, "#56b4e9"),
+    (Element::Key, "#56b4e9"),
+    (Element::Description, "dark_gray"),
+];
+
+const NO_COLOR_STYLES: &[(Element, &str)] = &[
+    (Element::Accent, "bold"),
+    (Element::Failure, "bold"),
+    (Element::Running, "italic"),
+    (Element::Aborted, "dim"),
+    (Element::NotBuilt, "dim"),
+    (Element::Cause, "italic"),
+    (Element::Stale, "italic"),
+    (Element::Insecure, "bold"),
+    (Element::HealthDown, "bold"),
+    (Element::Problem, "bold"),
+    (Element::Match, "bold underlined"),
+    (Element::Selection, "underlined"),
+    (Element::Cursor, "reversed"),
+    (Element::LineNumber, "dim"),
+    (Element::CursorLineNumber, "bold"),
+    (Element::Removed, "dim"),
+    (Element::Added, "bold"),
+    (Element::Key, "bold"),
+    (Element::Description, "dim"),
+];
+
+const MODIFIERS: [(&str, Modifier); 7] = [
+    ("bold", Modifier::BOLD),
+    ("dim", Modifier::DIM),
+    ("italic", Modifier::ITALIC),
+    ("underlined", Modifier::UNDERLINED),
+    ("blink", Modifier::SLOW_BLINK),
+    ("reversed", Modifier::REVERSED),
+    ("crossed_out", Modifier::CROSSED_OUT),
+];
+
+/// parse a style like `bold red on black`: modifiers, a foreground color, and `on` a background
+/// color, in any order. colors are names (`light_blue`), 256-color indexes (`208`), or
+/// `#rrggbb`.
+pub fn parse_style(spec: &str) -> Result<Style, String> {
+    let mut style = Style::default();
+    let mut words = spec.split_whitespace();
+    while let Some(word) = words.next() {
+        let word = word.to_ascii_lowercase();
+        if let Some((_, modifier)) = MODIFIERS.iter().find(|(name, _)| *name == word) {
+            style = style.add_modifier(*modifier);
+        } else if word == "on" {
+            let color = words
+                .next()
+                .ok_or_else(|| format!("missing a color after \"on\" in {:?}", spec))?;
+            style = style.bg(parse_color(color)?);
+        } else {
+            style = style.fg(parse_color(&word)?);
+        }
+    }
+    Ok(style)
+}
+
+fn parse_color(color: &str) -> Result<Color, String> {
+    Color::from_str(color).map_err(|_| format!("unknown color {:?}", color))
+}
+
+/// a style, as written in a theme file
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(try_from = "String")]
+struct ThemeStyle(Style);
+
+impl TryFrom<String> for ThemeStyle {
+    type Error = String;
+
+    fn try_from(spec: String) -> Result<Self, Self::Error> {
+        parse_style(&spec).map(ThemeStyle)
+    }
+}
+
+/// a theme file: styles to use instead of the preset's
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ThemeFile {
+    /// show builds' states in trends as symbols rather than colored blocks
+    symbols: Option<bool>,
+    styles: HashMap<Element, ThemeStyle>,
+}
+
+/// the characters the UI is drawn with
+#[derive(Debug)]
+pub struct Symbols {
+    pub success: &'static str,
+    pub failure: &'static str,
+    pub running: &'static str,
+    pub aborted: &'static str,
+    pub not_built: &'static str,
+    pub problem: &'static str,
+    pub collapsed: &'static str,
+    pub expanded: &'static str,
+    /// between the two sides of a diff
+    pub separator: &'static str,
+    /// a build in a job's trend
+    pub trend: &'static str,
+    /// next to the column the jobs are sorted by
+    pub ascending: &'static str,
+    pub descending: &'static str,
+    pub border: border::Set,
+    pub vertical_scrollbar: scrollbar::Set,
+    pub horizontal_scrollbar: scrollbar::Set,
+}
+
+const UNICODE_SYMBOLS: Symbols = Symbols {
+    success: "✔",
+    failure: "✘",
+    running: "●",
+    aborted: "⊘",
+    not_built: "⏸",
+    problem: "●",
+    collapsed: "▸",
+    expanded: "▾",
+    separator: "│",
+    trend: "■",
+    ascending: "▲",
+    descending: "▼",
+    border: border::PLAIN,
+    vertical_scrollbar: scrollbar::DOUBLE_VERTICAL,
+    horizontal_scrollbar: scrollbar::DOUBLE_HORIZONTAL,
+};
+
+const ASCII_SYMBOLS: Symbols = Symbols {
+    success: "+",
+    failure: "x",
+    running: "*",
+    aborted: "/",
+    not_built: "-",
+    problem: "!",
+    collapsed: ">",
+    expanded: "v",
+    separator: "|",
+    trend: "#",
+    ascending: "^",
+    descending: "v",
+    border: border::Set {
+        top_left: "+",
+        top_right: "+",
+        bottom_left: "+",
+        bottom_right: "+",
+        vertical_left: "|",
+        vertical_right: "|",
+        horizontal_top: "-",
+        horizontal_bottom: "-",
+    },
+    vertical_scrollbar: scrollbar::Set {
+        track: "|",
+        thumb: "#",
+        begin: "^",
+        end: "v",
+    },
+    horizontal_scrollbar: scrollbar::Set {
+        track: "-",
+        thumb: "#",
+        begin: "<",
+        end: ">",
+    },
+};
+
+/// the symbols to draw with: Unicode, or just ASCII for terminals that can't show it
+pub fn symbols(ascii: bool) -> &'static Symbols {
+    if ascii {
+        &ASCII_SYMBOLS
+    } else {
+        &UNICODE_SYMBOLS
+    }
+}
+
+/// whether the user asked for no colors, with a non-empty `NO_COLOR` (see https://no-color.org)
+pub fn no_color() -> bool {
+    env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
+/// how everything in the UI looks
+#[derive(Debug, Clone)]
+pub struct Theme {
+    styles: HashMap<Element, Style>,
+    /// show builds' states in trends as symbols rather than colored blocks, so they aren't told
+    /// apart by color alone
+    pub state_symbols: bool,
+    pub symbols: &'static Symbols,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::new(Preset::Default, false)
+    }
+}
+
+impl Theme {
+    pub fn new(preset: Preset, ascii: bool) -> Self {
+        let styles = match preset {
+            Preset::Default => DEFAULT_STYLES,
+            Preset::ColorBlind => COLOR_BLIND_STYLES,
+            Preset::NoColor => NO_COLOR_STYLES,
+        };
+        Self {
+            styles: styles
+                .iter()
+                .map(|(element, spec)| {
+                    let style = parse_style(spec).expect("built-in styles are valid");
+                    (*element, style)
+                })
+                .collect(),
+            state_symbols: preset != Preset::Default,
+            symbols: symbols(ascii),
+        }
+    }
+
+    /// use the styles in a theme file (TOML, or YAML if the extension says so) instead
+    pub fn load(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let content = fs::read_to_string(path)?;
+        let file: ThemeFile = match path.extension().and_then(|e| e.to_str()) {
+            Some("yaml" | "yml") => serde_yaml::from_str(&content)?,
+            _ => toml::from_str(&content)?,
+        };
+        if let Some(symbols) = file.symbols {
+            self.state_symbols = symbols;
+        }
+        for (element, ThemeStyle(style)) in file.styles {
+            self.set(element, style);
+        }
+        Ok(())
+    }
+
+    pub fn set(&mut self, element: Element, style: Style) {
+        self.styles.insert(element, style);
+    }
+
+    pub fn style(&self, element: Element) -> Style {
+        self.styles.get(&element).copied().unwrap_or_default()
+    }
+
+    /// the style and symbol of a build in `state`
+    pub fn build_state(&self, state: &BuildState) -> (Style, &'static str) {
+        let (element, symbol) = match state {
+            BuildState::Success => (Element::Success, self.symbols.success),
+            BuildState::Failure => (Element::Failure, self.symbols.failure),
+            BuildState::Unknown => (Element::Running, self.symbols.running),
+            BuildState::Aborted => (Element::Aborted, self.symbols.aborted),
+            BuildState::NotBuilt => (Element::NotBuilt, self.symbols.not_built),
+        };
+        (self.style(element), symbol)
+    }
+}
@@ -1,4 +1,4 @@
-use std::{error::Error, io, panic};
+use std::{error::Error, io, panic, sync::Once};
 
 use crossterm::{
     event::{DisableMouseCapture, EnableMouseCapture},
@@ -9,26 +9,45 @@ use ratatui::{backend::Backend, Terminal};
 
 use crate::{app::App, event::EventHandler, ui};
 
+static PANIC_HOOK: Once = Once::new();
+
 #[derive(Debug)]
 pub struct Tui<B: Backend> {
     terminal: Terminal<B>,
     pub events: EventHandler,
+    /// whether the mouse is captured; if it isn't, the terminal can select text as usual
+    mouse_capture: bool,
 }
 
 impl<B: Backend> Tui<B> {
     pub fn new(terminal: Terminal<B>, events: EventHandler) -> Self {
-        Self { terminal, events }
+        Self {
+            terminal,
+            events,
+            mouse_capture: true,
+        }
+    }
+
+    pub fn mouse_capture(mut self, mouse_capture: bool) -> Self {
+        self.mouse_capture = mouse_capture;
+        self
     }
 
     pub fn init(&mut self) -> Result<(), Box<dyn Error>> {
         terminal::enable_raw_mode()?;
-        execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
-        let panic_hook = panic::take_hook();
-        panic::set_hook(Box::new(move |panic| {
-            Self::reset().expect("failed to reset the terminal");
-            // TODO: figure out how to show the cursor while respecting lifetimes
-            panic_hook(panic);
-        }));
+        execute!(io::stdout(), EnterAlternateScreen)?;
+        if self.mouse_capture {
+            execute!(io::stdout(), EnableMouseCapture)?;
+        }
+        // init is called again after suspending, but the hook only needs setting once
+        PANIC_HOOK.call_once(|| {
+            let panic_hook = panic::take_hook();
+            panic::set_hook(Box::new(move |panic| {
+                Self::reset().expect("failed to reset the terminal");
+                // TODO: figure out how to show the cursor while respecting lifetimes
+                panic_hook(panic);
+            }));
+        });
         self.terminal.hide_cursor()?;
         self.terminal.clear()?;
         Ok(())
@@ -39,12 +58,34 @@ impl<B: Backend> Tui<B> {
         Ok(())
     }
 
+    /// capture the mouse if it isn't, or let it go if it is; returns whether it's captured now
+    pub fn toggle_mouse_capture(&mut self) -> Result<bool, Box<dyn Error>> {
+        self.mouse_capture = !self.mouse_capture;
+        if self.mouse_capture {
+            execute!(io::stdout(), EnableMouseCapture)?;
+        } else {
+            execute!(io::stdout(), DisableMouseCapture)?;
+        }
+        Ok(self.mouse_capture)
+    }
+
     pub fn reset() -> Result<(), Box<dyn Error>> {
         terminal::disable_raw_mode()?;
         execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture)?;
         Ok(())
     }
 
+    /// hand the terminal over to `f` (to run a pager or editor), then take it back
+    pub async fn suspend<T>(&mut self, f: impl FnOnce() -> T) -> Result<T, Box<dyn Error>> {
+        self.events.pause().await;
+        Self::reset()?;
+        self.terminal.show_cursor()?;
+        let result = f();
+        self.init()?;
+        self.events.resume();
+        Ok(result)
+    }
+
     pub fn exit(&mut self) -> Result<(), Box<dyn Error>> {
         Self::reset()?;
         self.terminal.show_cursor()?;
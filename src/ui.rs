@@ -1,31 +1,359 @@
+use std::{collections::HashMap, time::Duration};
+
+use chrono::{DateTime, Utc};
 use ratatui::{
     layout::{Constraint, Layout, Rect},
-    style::Color,
-    widgets::{Block, List, Paragraph, Wrap},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Cell, Clear, List, ListItem, ListState, Paragraph, Row, Table},
     Frame,
 };
-use tui_scrollview::ScrollView;
+use unicode_width::UnicodeWidthStr;
 
-use crate::{app::App, jenkins::JenkinsResult};
+use crate::{
+    app::{shown_logs, App},
+    diff::DiffView,
+    health::HealthState,
+    jenkins::{JenkinsResult, JobSort, SortColumn},
+    keymap::Scope,
+    layout::PaneAreas,
+    log_view::{LogView, ShownLog},
+    theme::{Element, Theme},
+};
 
-static ACCENT_COLOR: Color = Color::Magenta;
 static HIGHLIGHT_SYMBOL: &str = ">> ";
+/// most problems to list under the logs at once
+const MAX_PROBLEM_ROWS: usize = 6;
+/// most suggestions to show in the command palette at once
+const MAX_SUGGESTION_ROWS: usize = 10;
+/// widest the help overlay and command palette get
+const POPUP_WIDTH: u16 = 72;
+/// builds shown in each job's trend
+const TREND_LENGTH: usize = 5;
+
+/// the part of `area` that's `width` by `height`, centered horizontally, `top` rows down (or
+/// centered vertically, if there's no `top`)
+fn popup_area(area: Rect, width: u16, height: u16, top: Option<u16>) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    let y = match top {
+        Some(top) => top.min(area.height - height),
+        None => (area.height - height) / 2,
+    };
+    Rect::new(area.x + (area.width - width) / 2, area.y + y, width, height)
+}
+
+/// a bordered block, drawn with the theme's symbols
+fn bordered(theme: &Theme) -> Block<'static> {
+    Block::bordered().border_set(theme.symbols.border)
+}
+
+/// how long ago something was, in its biggest unit, like `3m ago`
+fn age(since: chrono::Duration) -> String {
+    let secs = since.num_seconds().max(0);
+    match secs {
+        0..60 => format!("{}s ago", secs),
+        60..3600 => format!("{}m ago", secs / 60),
+        3600..86400 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86400),
+    }
+}
+
+/// a duration in its two biggest units, like `3m 12s`
+fn short_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (days, hours, minutes) = (secs / 86400, secs / 3600 % 24, secs / 60 % 60);
+    if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m {}s", minutes, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+/// the job list's header, with an arrow on the column the jobs are sorted by
+fn job_header(sort: JobSort, theme: &Theme, servers: bool) -> Row<'static> {
+    let arrow = if sort.column.descending() != sort.reversed {
+        theme.symbols.descending
+    } else {
+        theme.symbols.ascending
+    };
+    let server = servers.then_some((None, "Server"));
+    let columns = [(Some(SortColumn::State), "")]
+        .into_iter()
+        .chain(server)
+        .chain([
+            (Some(SortColumn::Name), "Job"),
+            (Some(SortColumn::Build), "Build"),
+            (Some(SortColumn::Updated), "Age"),
+            (Some(SortColumn::Duration), "Duration"),
+            (None, "ETA"),
+            (None, "Trend"),
+        ]);
+    Row::new(columns.map(|(column, title)| {
+        if column == Some(sort.column) && title.is_empty() {
+            arrow.to_string()
+        } else if column == Some(sort.column) {
+            format!("{} {}", title, arrow)
+        } else {
+            title.to_string()
+        }
+    }))
+    .style(Style::new().add_modifier(Modifier::BOLD))
+}
+
+/// a build in the job list: its state's symbol, its name in its state's style along with any
+/// known failure causes, its number, how long ago it was updated, how long it took, how much
+/// longer it should take if it's running, and the states of the job's last few builds up to it.
+/// `history` is all of the job's builds, newest first. in the all-servers view, the server it's
+/// from comes after its state.
+fn job_row<'a>(
+    job: &'a JenkinsResult,
+    history: &[&JenkinsResult],
+    theme: &Theme,
+    now: DateTime<Utc>,
+    servers: bool,
+) -> Row<'a> {
+    let (style, symbol) = theme.build_state(&job.build_state);
+    let mut name = Line::styled(job.name.as_str(), style);
+    if !job.causes.is_empty() {
+        let causes: Vec<&str> = job.causes.iter().map(|c| c.name.as_str()).collect();
+        name.push_span(Span::styled(
+            format!(" [{}]", causes.join(", ")),
+            theme.style(Element::Cause),
+        ));
+    }
+    let duration = job
+        .timing
+        .map(|timing| short_duration(timing.elapsed(now)))
+        .unwrap_or_default();
+    let eta = match job.timing.and_then(|timing| timing.remaining(now)) {
+        Some(remaining) => match remaining.to_std() {
+            Ok(remaining) => format!("~{}", short_duration(remaining)),
+            Err(_) => "overdue".to_string(),
+        },
+        None => String::new(),
+    };
+    let mut trend: Vec<Span> = history
+        .iter()
+        .filter(|build| build.build_number <= job.build_number)
+        .take(TREND_LENGTH)
+        .map(|build| {
+            let (style, symbol) = theme.build_state(&build.build_state);
+            let symbol = if theme.state_symbols {
+                symbol
+            } else {
+                theme.symbols.trend
+            };
+            Span::styled(symbol, style)
+        })
+        .collect();
+    // oldest on the left
+    trend.reverse();
+    let server = servers.then(|| Cell::new(job.server.as_deref().unwrap_or_default()));
+    let cells = [Cell::new(Span::styled(symbol, style))]
+        .into_iter()
+        .chain(server);
+    Row::new(cells.chain([
+        Cell::new(name),
+        Cell::new(format!("#{}", job.build_number)),
+        Cell::new(age(now.signed_duration_since(job.updated))),
+        Cell::new(duration),
+        Cell::new(eta),
+        Cell::new(Line::from(trend)),
+    ]))
+}
+
+/// the job list: a row for each build, under a header. the all-servers view has a column for
+/// which server each build is from, if `servers`. rows borrow from `jobs` rather than copying
+/// them, since this runs every frame.
+fn job_table<'a>(
+    jobs: &'a [JenkinsResult],
+    sort: JobSort,
+    theme: &Theme,
+    servers: bool,
+) -> Table<'a> {
+    // jobs on different servers can have the same name
+    fn key(job: &JenkinsResult) -> (Option<&str>, &str) {
+        (job.server.as_deref(), &job.name)
+    }
+    let mut histories: HashMap<_, Vec<&JenkinsResult>> = HashMap::new();
+    for job in jobs {
+        histories.entry(key(job)).or_default().push(job);
+    }
+    for history in histories.values_mut() {
+        history.sort_by_key(|build| std::cmp::Reverse(build.build_number));
+    }
+    let now = Utc::now();
+    let rows = jobs
+        .iter()
+        .map(|job| job_row(job, &histories[&key(job)], theme, now, servers));
+    let build_width = jobs
+        .iter()
+        .map(|job| format!("#{}", job.build_number).len())
+        .max()
+        .unwrap_or_default()
+        .max("Build ▼".width()) as u16;
+    let server_width = jobs
+        .iter()
+        .filter_map(|job| Some(job.server.as_ref()?.width()))
+        .max()
+        .unwrap_or_default()
+        .max("Server".width()) as u16;
+    let server = servers.then_some(Constraint::Length(server_width));
+    let widths = [Constraint::Length(1)].into_iter().chain(server).chain([
+        Constraint::Fill(1),
+        Constraint::Length(build_width),
+        Constraint::Length(8),
+        Constraint::Length(10),
+        Constraint::Length(8),
+        Constraint::Length(TREND_LENGTH as u16),
+    ]);
+    Table::new(rows, widths)
+        .header(job_header(sort, theme, servers))
+        .highlight_symbol(HIGHLIGHT_SYMBOL)
+}
+
+/// each of the active pane's actions, then each of the global ones, with the keys that do them
+fn render_help(app: &mut App, frame: &mut Frame) {
+    let theme = &app.theme;
+    let accent = theme.style(Element::Accent);
+    let scope = Scope::of_pane(app.active_pane);
+    let pane = match scope {
+        Scope::Servers => "Server List",
+        Scope::Jobs => "Job List",
+        Scope::Logs | Scope::Global => "Job Logs",
+    };
+    let mut lines = vec![];
+    for (title, scope) in [(pane, scope), ("Everywhere", Scope::Global)] {
+        if !lines.is_empty() {
+            lines.push(Line::raw(""));
+        }
+        lines.push(Line::styled(title, accent.add_modifier(Modifier::BOLD)));
+        for (action, keys) in app.keymap.bindings(scope) {
+            lines.push(Line::from(vec![
+                Span::styled(
+                    format!("{:>16}  ", keys.join(", ")),
+                    theme.style(Element::Key),
+                ),
+                Span::raw(action.description()),
+            ]));
+        }
+    }
+
+    let area = popup_area(frame.area(), POPUP_WIDTH, lines.len() as u16 + 2, None);
+    let max_scroll = (lines.len() as u16).saturating_sub(area.height.saturating_sub(2));
+    let scroll = app.help_scroll.get_or_insert(0);
+    *scroll = (*scroll).min(max_scroll);
+    let block = bordered(theme)
+        .title("Keys")
+        .title(Line::from("j/k to scroll, any other key to close").right_aligned())
+        .border_style(accent);
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(lines).block(block).scroll((*scroll, 0)),
+        area,
+    );
+}
+
+/// the command being typed, with the commands (or names) matching it underneath
+fn render_palette(app: &App, frame: &mut Frame) {
+    let Some(palette) = &app.palette else {
+        return;
+    };
+    let suggestions = app.palette_suggestions();
+    let rows = suggestions.len().min(MAX_SUGGESTION_ROWS) as u16;
+    let area = popup_area(frame.area(), POPUP_WIDTH, rows + 3, Some(2));
+    let block = bordered(&app.theme)
+        .title("Command")
+        .title(Line::from("tab to complete, enter to run").right_aligned())
+        .border_style(app.theme.style(Element::Accent));
+    let inner = block.inner(area);
+    let [input, list] = Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]).areas(inner);
+    let text = format!(":{}", palette.input);
+    frame.set_cursor_position((input.x + text.width() as u16, input.y));
+
+    // line the descriptions up
+    let width = suggestions
+        .iter()
+        .map(|suggestion| suggestion.text.width())
+        .max()
+        .unwrap_or(0);
+    let items = suggestions.iter().map(|suggestion| {
+        ListItem::new(Line::from(vec![
+            Span::raw(format!("{:<width$}  ", suggestion.text)),
+            Span::styled(
+                suggestion.description.clone(),
+                app.theme.style(Element::Description),
+            ),
+        ]))
+    });
+    frame.render_widget(Clear, area);
+    frame.render_widget(block, area);
+    frame.render_widget(Paragraph::new(text), input);
+    frame.render_stateful_widget(
+        List::new(items).highlight_style(Style::new().add_modifier(Modifier::REVERSED)),
+        list,
+        &mut ListState::default().with_selected(Some(palette.selected)),
+    );
+}
 
 pub fn render(app: &mut App, frame: &mut Frame) {
+    let theme = &app.theme;
+    let accent = theme.style(Element::Accent);
     let [main_app, status] =
         Layout::vertical([Constraint::Percentage(100), Constraint::Min(3)]).areas(frame.area());
-    let [server_list, job_pane] =
-        Layout::horizontal([Constraint::Ratio(1, 3), Constraint::Ratio(2, 3)]).areas(main_app);
-    let [job_list, job_logs] =
-        Layout::vertical([Constraint::Percentage(20), Constraint::Fill(1)]).areas(job_pane);
+    let zoomed = app.zoomed.then_some(app.active_pane);
+    app.areas = app.layout.areas(main_app, !app.servers_hidden(), zoomed);
+    let PaneAreas {
+        server_list,
+        job_list,
+        job_logs,
+        ..
+    } = app.areas;
 
-    let status_text =
-        Paragraph::new(app.status.to_string()).block(Block::bordered().title("Status"));
+    // a search being typed replaces the status, with the cursor at the end of it
+    let status_text = match &app.prompt {
+        Some(prompt) => {
+            let text = format!("/{}", prompt);
+            frame.set_cursor_position((status.x + 1 + text.width() as u16, status.y + 1));
+            Paragraph::new(text).block(bordered(theme).title("Search"))
+        }
+        None => Paragraph::new(app.status.to_string()).block(bordered(theme).title("Status")),
+    };
 
-    let mut server_list_block = Block::bordered().title("Server List [1]");
-    let server_list_list = List::new(app.servers.servers.keys().cloned())
+    let mut server_list_block = bordered(theme).title("Server List [1]");
+    let server_list_items = app.servers.servers.iter().map(|(name, entry)| {
+        let server = &entry.server;
+        let health_style = match server.health.state {
+            HealthState::Unknown => Style::default(),
+            HealthState::Ok => theme.style(Element::HealthOk),
+            HealthState::Degraded => theme.style(Element::HealthDegraded),
+            HealthState::Unreachable | HealthState::AuthFailed => theme.style(Element::HealthDown),
+        };
+        let mut line = Line::from(vec![
+            Span::raw(name.clone()),
+            Span::raw(" "),
+            Span::styled(format!("[{}]", server.health), health_style),
+        ]);
+        if server.timings_unavailable.is_some() {
+            line.push_span(Span::raw(" (no timings)"));
+        }
+        if server.insecure_skip_verify {
+            line.push_span(Span::styled(
+                " (TLS NOT VERIFIED)",
+                theme.style(Element::Insecure),
+            ));
+        }
+        ListItem::new(line)
+    });
+    let all_servers_item = app.all_servers_idx().map(|_| ListItem::new("All servers"));
+    let server_list_list = List::new(server_list_items.chain(all_servers_item))
         .highlight_symbol(HIGHLIGHT_SYMBOL)
-        .highlight_style(ACCENT_COLOR)
+        .highlight_style(accent)
         .repeat_highlight_symbol(true);
     frame.render_stateful_widget(
         server_list_list,
@@ -33,65 +361,144 @@ pub fn render(app: &mut App, frame: &mut Frame) {
         &mut app.servers.server_state,
     );
 
-    let mut job_list_block = Block::bordered().title("Job List [2]");
-    if let Some((stateful_jobs, _)) = app.get_current_server_jobs() {
-        // FIXME: weird borrow-checker nonsense
-        let mut cache_vec = vec![JenkinsResult::default(); stateful_jobs.jobs.len()];
-        cache_vec.clone_from_slice(&stateful_jobs.jobs);
-        let job_list_list = List::new(cache_vec)
-            .highlight_symbol(HIGHLIGHT_SYMBOL)
-            // .highlight_style(ACCENT_COLOR)
-            .repeat_highlight_symbol(true);
+    let mut job_list_block = bordered(theme).title("Job List [2]");
+    if let Some(marked) = app.marked_build() {
+        job_list_block = job_list_block.title(format!("marked: {}", marked));
+    }
+    let all_servers = app.all_servers_selected();
+    if let Some(stateful_jobs) = app.servers.selected_jobs_mut() {
+        if let Some(stale_since) = stateful_jobs.stale_since {
+            job_list_block = job_list_block.title(
+                Line::styled(
+                    format!("stale, cached {}", stale_since.format("%F %T")),
+                    theme.style(Element::Stale),
+                )
+                .right_aligned(),
+            );
+        }
         frame.render_stateful_widget(
-            job_list_list,
+            job_table(&stateful_jobs.jobs, app.job_sort, theme, all_servers),
             job_list_block.inner(job_list),
             &mut stateful_jobs.job_state,
         );
     }
 
-    let mut job_logs_block = Block::bordered().title("Job Logs [3]");
+    let mut job_logs_block = bordered(theme).title("Job Logs [3]");
     let job_logs_inner = job_logs_block.inner(job_logs);
-    // if there's no selected job, don't bother generating the paragraph for the
-    // logs nor the scrollview.
-    if let Some((stateful_job, _)) = app.get_current_server_jobs() {
-        if let Some(job_idx) = stateful_job.job_state.selected() {
-            if !stateful_job.jobs[job_idx].logs.is_empty() {
-                // TODO: fight borrow checker on this clone
-                // TODO: figure out how to handle coloring
-                let mut paragraph = Paragraph::new(stateful_job.jobs[job_idx].logs.clone());
-
-                // allow hitting `w` to wrap the text.
-                let mut width: u16 = paragraph.line_width().try_into().unwrap();
-                // if we're configured to wrap the text, the max width is the container, and we have
-                // to tell the paragraph that it's gonna be wrapped
-                if app.wrap_logs {
-                    paragraph = paragraph.wrap(Wrap { trim: false });
-                    width = job_logs_inner.as_size().width;
-                }
-
-                let paragraph_rect =
-                    Rect::new(0, 0, width, paragraph.line_count(width).try_into().unwrap());
-
-                // the scroll view must be sized to the content!
-                let mut job_logs_scrollview = ScrollView::new(paragraph_rect.as_size());
-                // render the paragraph into the scroll view
-                job_logs_scrollview.render_widget(paragraph, paragraph_rect);
-                // render the scroll view into the frame. the scroll view should be larger than the
-                // job_logs_inner, and the scroll view will add scrollbars as necessary.
+    // if there's no selected job, don't bother rendering the logs
+    let wrap_logs = app.wrap_logs;
+    let line_numbers = app.line_numbers;
+    let show_problems = app.show_problems;
+    let App {
+        servers,
+        log_scroll_state,
+        marked,
+        diff,
+        folded,
+        search,
+        ..
+    } = app;
+    let selected_job = servers
+        .selected_jobs()
+        .and_then(|stateful_jobs| stateful_jobs.jobs.get(stateful_jobs.job_state.selected()?));
+    let marked = marked
+        .as_ref()
+        .and_then(|marked| servers.find_build(marked));
+    // the diff is only good for the builds it was worked out for
+    let diff = diff.as_ref().filter(|diff| {
+        selected_job.is_some_and(|job| job.link == diff.selected)
+            && marked.is_some_and(|marked| marked.link == diff.marked)
+    });
+    if let (Some(job), Some(marked), Some(diff)) = (selected_job, marked, diff) {
+        job_logs_block = job_logs_block.title(format!("diff: {} (marked) vs {}", marked, job));
+        frame.render_stateful_widget(
+            DiffView::new(&marked.logs, &job.logs, &diff.diff, theme),
+            job_logs_inner,
+            log_scroll_state,
+        );
+    } else if let Some(job) = selected_job {
+        if !job.logs.is_empty() {
+            // folded, if it is, with the problems' line numbers still from the original
+            let shown = shown_logs(servers, folded).unwrap_or(ShownLog::Whole(&job.logs));
+            let problems = shown.problems();
+            let mut log_area = job_logs_inner;
+            if !job.causes.is_empty() {
+                let [causes, logs] = Layout::vertical([
+                    Constraint::Length(job.causes.len() as u16 + 1),
+                    Constraint::Fill(1),
+                ])
+                .areas(log_area);
+                log_area = logs;
+                let cause_lines: Vec<Line> = job
+                    .causes
+                    .iter()
+                    .map(|cause| {
+                        Line::from(vec![
+                            Span::styled(cause.name.clone(), theme.style(Element::Cause)),
+                            Span::raw(format!(" ({}, line {}) ", cause.category, cause.line + 1)),
+                            Span::raw(cause.description.clone()),
+                        ])
+                    })
+                    .collect();
+                frame.render_widget(
+                    Paragraph::new(cause_lines).block(
+                        Block::new()
+                            .borders(Borders::BOTTOM)
+                            .border_set(theme.symbols.border),
+                    ),
+                    causes,
+                );
+            }
+            if show_problems && !problems.is_empty() {
+                let rows = problems.len().min(MAX_PROBLEM_ROWS);
+                let [logs, problem_list] =
+                    Layout::vertical([Constraint::Fill(1), Constraint::Length(rows as u16 + 1)])
+                        .areas(log_area);
+                log_area = logs;
+                // highlight the problem at (or just below) the top of the logs
+                let top = log_scroll_state.top_line(shown, wrap_logs);
+                let current = problems
+                    .partition_point(|problem| *problem < top)
+                    .min(problems.len() - 1);
+                // there could be thousands, so only lay out the ones that fit, around the current one
+                let first = current.saturating_sub(rows / 2).min(problems.len() - rows);
+                let problem_items = problems[first..first + rows].iter().map(|line| {
+                    format!(
+                        "{:>6}: {}",
+                        shown.original_line(*line) + 1,
+                        shown.line(*line)
+                    )
+                });
+                let problem_list_block = Block::new()
+                    .borders(Borders::TOP)
+                    .border_set(theme.symbols.border)
+                    .title(format!("Problems ({})", problems.len()));
                 frame.render_stateful_widget(
-                    job_logs_scrollview,
-                    job_logs_inner,
-                    &mut app.log_scroll_state,
+                    List::new(problem_items)
+                        .block(problem_list_block)
+                        .highlight_style(accent),
+                    problem_list,
+                    &mut ListState::default().with_selected(Some(current - first)),
                 );
             }
+            // TODO: figure out how to handle coloring
+            // only the visible rows are laid out, so this stays cheap however long the log is
+            frame.render_stateful_widget(
+                LogView::new(shown, theme)
+                    .wrap(wrap_logs)
+                    .line_numbers(line_numbers)
+                    .matches(search.as_ref().map_or(&[], |search| search.matches())),
+                log_area,
+                log_scroll_state,
+            );
         }
     }
 
     // highlight the active pane
     match app.active_pane {
-        1 => server_list_block = server_list_block.border_style(ACCENT_COLOR),
-        2 => job_list_block = job_list_block.border_style(ACCENT_COLOR),
-        3 => job_logs_block = job_logs_block.border_style(ACCENT_COLOR),
+        1 => server_list_block = server_list_block.border_style(accent),
+        2 => job_list_block = job_list_block.border_style(accent),
+        3 => job_logs_block = job_logs_block.border_style(accent),
         _ => {}
     }
 
@@ -99,4 +506,10 @@ pub fn render(app: &mut App, frame: &mut Frame) {
     frame.render_widget(server_list_block, server_list);
     frame.render_widget(job_list_block, job_list);
     frame.render_widget(job_logs_block, job_logs);
+
+    // popups go over everything else
+    if app.help_scroll.is_some() {
+        render_help(app, frame);
+    }
+    render_palette(app, frame);
 }